@@ -0,0 +1,134 @@
+use nalgebra_glm::Vec2;
+
+//Antialiasing del framebuffer, se elige al arrancar (`--aa`).
+//Cada pixel guarda varias muestras de color y profundidad que `resolve`
+//promedia al final del frame.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Antialiasing {
+    //una muestra en el centro del pixel
+    #[default]
+    None,
+    //rejilla de N x N muestras sombreadas cada una: es dibujar a N veces la
+    //resolucion y reducir
+    Ssaa(usize),
+    //N muestras de cobertura y profundidad, un solo sombreado por pixel
+    Msaa(usize),
+}
+
+//la cobertura de un pixel es una mascara de bits u32
+pub const MAX_SAMPLES: usize = 16;
+
+//Patrones estandar de D3D en dieciseisavos de pixel, relativos al centro
+const MSAA_2: [(f32, f32); 2] = [(4.0, 4.0), (-4.0, -4.0)];
+const MSAA_4: [(f32, f32); 4] = [(-2.0, -6.0), (6.0, -2.0), (-6.0, 2.0), (2.0, 6.0)];
+const MSAA_8: [(f32, f32); 8] = [
+    (1.0, -3.0),
+    (-1.0, 3.0),
+    (5.0, 1.0),
+    (-3.0, -5.0),
+    (-5.0, 5.0),
+    (-7.0, -1.0),
+    (3.0, 7.0),
+    (7.0, -7.0),
+];
+
+pub const NAMES: &str = "none, ssaa2, ssaa3, ssaa4, msaa2, msaa4, msaa8";
+
+impl Antialiasing {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Antialiasing::None),
+            "ssaa2" => Some(Antialiasing::Ssaa(2)),
+            "ssaa3" => Some(Antialiasing::Ssaa(3)),
+            "ssaa4" => Some(Antialiasing::Ssaa(4)),
+            "msaa2" => Some(Antialiasing::Msaa(2)),
+            "msaa4" => Some(Antialiasing::Msaa(4)),
+            "msaa8" => Some(Antialiasing::Msaa(8)),
+            _ => None,
+        }
+    }
+
+    pub fn samples(self) -> usize {
+        self.pattern().count()
+    }
+
+    pub fn pattern(self) -> SamplePattern {
+        let mut pattern = SamplePattern {
+            offsets: [Vec2::zeros(); MAX_SAMPLES],
+            count: 0,
+            shade_per_sample: false,
+        };
+        match self {
+            Antialiasing::None => pattern.push(Vec2::new(0.5, 0.5)),
+            Antialiasing::Ssaa(grid) => {
+                assert!((1..=4).contains(&grid), "unsupported SSAA factor {}", grid);
+                pattern.shade_per_sample = true;
+                for y in 0..grid {
+                    for x in 0..grid {
+                        let cell = |i: usize| (i as f32 + 0.5) / grid as f32;
+                        pattern.push(Vec2::new(cell(x), cell(y)));
+                    }
+                }
+            }
+            Antialiasing::Msaa(samples) => {
+                let offsets: &[(f32, f32)] = match samples {
+                    2 => &MSAA_2,
+                    4 => &MSAA_4,
+                    8 => &MSAA_8,
+                    _ => panic!("unsupported MSAA sample count {}", samples),
+                };
+                for &(x, y) in offsets {
+                    pattern.push(Vec2::new(0.5 + x / 16.0, 0.5 + y / 16.0));
+                }
+            }
+        }
+        pattern
+    }
+}
+
+//Posiciones de las muestras dentro del pixel (de 0 a 1). Es `Copy` para que
+//el rasterizador la lea una vez por triangulo.
+#[derive(Debug, Clone, Copy)]
+pub struct SamplePattern {
+    offsets: [Vec2; MAX_SAMPLES],
+    count: usize,
+    //SSAA: el fragment shader corre por muestra y no por pixel
+    pub shade_per_sample: bool,
+}
+
+impl SamplePattern {
+    fn push(&mut self, offset: Vec2) {
+        self.offsets[self.count] = offset;
+        self.count += 1;
+    }
+
+    pub fn offsets(&self) -> &[Vec2] {
+        &self.offsets[..self.count]
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_stay_inside_the_pixel() {
+        for name in NAMES.split(", ") {
+            let antialiasing = Antialiasing::from_name(name).unwrap();
+            let pattern = antialiasing.pattern();
+            assert!(pattern.count() <= MAX_SAMPLES);
+            for offset in pattern.offsets() {
+                assert!((0.0..1.0).contains(&offset.x) && (0.0..1.0).contains(&offset.y), "{}", name);
+            }
+            //centradas: sin antialiasing todo cubierto da el centro del pixel
+            let center = pattern.offsets().iter().sum::<Vec2>() / pattern.count() as f32;
+            assert!((center - Vec2::new(0.5, 0.5)).norm() < 1e-6, "{}", name);
+        }
+        assert_eq!(Antialiasing::Ssaa(3).samples(), 9);
+        assert_eq!(Antialiasing::None.pattern().offsets(), &[Vec2::new(0.5, 0.5)]);
+    }
+}
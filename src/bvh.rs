@@ -0,0 +1,394 @@
+use nalgebra_glm::Vec3;
+
+use crate::collision::closest_point_on_triangle;
+use crate::mesh::Mesh;
+
+//Jerarquia de cajas (BVH) para no recorrer todos los triangulos o cuerpos en
+//cada consulta: rayos, solapamiento con esferas y cajas, y el mas cercano.
+//Guarda indices de primitivas; la prueba exacta la hace quien consulta.
+
+//primitivas por hoja
+const LEAF_SIZE: usize = 4;
+//si al reajustar las cajas suman mas que esto veces lo de recien construido, se reconstruye
+const REBUILD_FACTOR: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn empty() -> Self {
+        Aabb { min: Vec3::repeat(f32::INFINITY), max: Vec3::repeat(f32::NEG_INFINITY) }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Self {
+        points.into_iter().fold(Aabb::empty(), |bounds, point| bounds.union(&Aabb { min: *point, max: *point }))
+    }
+
+    pub fn around_sphere(center: &Vec3, radius: f32) -> Self {
+        Aabb { min: center - Vec3::repeat(radius), max: center + Vec3::repeat(radius) }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.inf(&other.min), max: self.max.sup(&other.max) }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    fn surface_area(&self) -> f32 {
+        let size = (self.max - self.min).sup(&Vec3::zeros());
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    pub fn distance_squared(&self, point: &Vec3) -> f32 {
+        (self.min - point).sup(&(point - self.max)).sup(&Vec3::zeros()).norm_squared()
+    }
+
+    pub fn overlaps_sphere(&self, center: &Vec3, radius: f32) -> bool {
+        self.distance_squared(center) <= radius * radius
+    }
+
+    //distancia a la que el rayo entra a la caja (0 si empieza adentro), hasta `max_t`
+    pub fn ray_entry(&self, ray: &Ray, max_t: f32) -> Option<f32> {
+        let inverse = Vec3::repeat(1.0).component_div(&ray.direction);
+        let t1 = (self.min - ray.origin).component_mul(&inverse);
+        let t2 = (self.max - ray.origin).component_mul(&inverse);
+        let near = t1.inf(&t2).max().max(0.0);
+        let far = t1.sup(&t2).min().min(max_t);
+        (near <= far).then_some(near)
+    }
+}
+
+//rayo con direccion unitaria: las distancias quedan en unidades de mundo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray { origin, direction: direction.normalize() }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    bounds: Aabb,
+    //hoja: primitivas `order[start..start + count]`; interno (count 0): el
+    //hijo izquierdo va justo despues y el derecho en `start`
+    start: usize,
+    count: usize,
+}
+
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    order: Vec<usize>,
+    //suma de areas al construir, para saber cuando reconstruir
+    built_cost: f32,
+}
+
+impl Bvh {
+    //divide por la mediana de los centros en el eje mas largo
+    pub fn build(bounds: &[Aabb]) -> Self {
+        let mut bvh = Bvh { nodes: Vec::new(), order: (0..bounds.len()).collect(), built_cost: 0.0 };
+        if !bounds.is_empty() {
+            bvh.split(bounds, 0, bounds.len());
+        }
+        bvh.built_cost = bvh.cost();
+        bvh
+    }
+
+    fn split(&mut self, bounds: &[Aabb], start: usize, end: usize) -> usize {
+        let index = self.nodes.len();
+        let primitives = &mut self.order[start..end];
+        let node_bounds = primitives.iter().fold(Aabb::empty(), |total, &i| total.union(&bounds[i]));
+        self.nodes.push(BvhNode { bounds: node_bounds, start, count: end - start });
+        if end - start <= LEAF_SIZE {
+            return index;
+        }
+
+        let centers = Aabb::from_points(&primitives.iter().map(|&i| bounds[i].center()).collect::<Vec<_>>());
+        let axis = (centers.max - centers.min).imax();
+        let middle = (end - start) / 2;
+        primitives.select_nth_unstable_by(middle, |&a, &b| bounds[a].center()[axis].total_cmp(&bounds[b].center()[axis]));
+        self.split(bounds, start, start + middle);
+        let right = self.split(bounds, start + middle, end);
+        self.nodes[index] = BvhNode { bounds: node_bounds, start: right, count: 0 };
+        index
+    }
+
+    fn cost(&self) -> f32 {
+        self.nodes.iter().map(|node| node.bounds.surface_area()).sum()
+    }
+
+    //caja de todo el arbol
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::empty(), |root| root.bounds)
+    }
+
+    //las primitivas se movieron: reajusta las cajas desde las hojas (los
+    //hijos van despues del padre) y reconstruye si el arbol se degrado
+    pub fn update(&mut self, bounds: &[Aabb]) {
+        if bounds.len() != self.order.len() {
+            *self = Bvh::build(bounds);
+            return;
+        }
+        for index in (0..self.nodes.len()).rev() {
+            let node = self.nodes[index];
+            self.nodes[index].bounds = if node.count > 0 {
+                self.order[node.start..node.start + node.count]
+                    .iter()
+                    .fold(Aabb::empty(), |total, &i| total.union(&bounds[i]))
+            } else {
+                self.nodes[index + 1].bounds.union(&self.nodes[node.start].bounds)
+            };
+        }
+        if self.cost() > REBUILD_FACTOR * self.built_cost {
+            *self = Bvh::build(bounds);
+        }
+    }
+
+    //primitivas de las hojas cuyas cajas pasan `test` (candidatas, no exactas)
+    pub fn query(&self, test: impl Fn(&Aabb) -> bool, mut visit: impl FnMut(usize)) {
+        let mut stack = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !test(&node.bounds) {
+                continue;
+            }
+            if node.count > 0 {
+                self.order[node.start..node.start + node.count].iter().for_each(|&i| visit(i));
+            } else {
+                stack.extend([node.start, index + 1]);
+            }
+        }
+    }
+
+    //primer impacto del rayo hasta `max_t`; `hit` da la distancia exacta a una primitiva
+    pub fn ray_cast(&self, ray: &Ray, max_t: f32, hit: impl FnMut(usize) -> Option<f32>) -> Option<(usize, f32)> {
+        self.closest(max_t, |bounds, limit| bounds.ray_entry(ray, limit), hit)
+    }
+
+    //primitiva mas cercana a `point`; `distance` da la distancia exacta a una primitiva
+    pub fn nearest(&self, point: &Vec3, mut distance: impl FnMut(usize) -> f32) -> Option<(usize, f32)> {
+        let entry = |bounds: &Aabb, limit: f32| Some(bounds.distance_squared(point).sqrt()).filter(|&d| d <= limit);
+        self.closest(f32::INFINITY, entry, |i| Some(distance(i)))
+    }
+
+    //busqueda del menor valor: se visita primero el hijo mas prometedor y se
+    //descartan las cajas que no pueden mejorar lo encontrado
+    fn closest(
+        &self,
+        max: f32,
+        entry: impl Fn(&Aabb, f32) -> Option<f32>,
+        mut value: impl FnMut(usize) -> Option<f32>,
+    ) -> Option<(usize, f32)> {
+        let mut best = None;
+        let mut limit = max;
+        let mut stack: Vec<(usize, f32)> = self.nodes.first().and_then(|root| entry(&root.bounds, limit)).map(|t| (0, t)).into_iter().collect();
+        while let Some((index, bound)) = stack.pop() {
+            if bound > limit {
+                continue;
+            }
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                for &primitive in &self.order[node.start..node.start + node.count] {
+                    if let Some(t) = value(primitive).filter(|&t| t <= limit) {
+                        (best, limit) = (Some((primitive, t)), t);
+                    }
+                }
+                continue;
+            }
+            let mut children = [index + 1, node.start].map(|child| (child, entry(&self.nodes[child].bounds, limit)));
+            //el mas cercano queda arriba de la pila
+            if children[0].1.unwrap_or(f32::INFINITY) < children[1].1.unwrap_or(f32::INFINITY) {
+                children.swap(0, 1);
+            }
+            stack.extend(children.into_iter().filter_map(|(child, t)| Some((child, t?))));
+        }
+        best
+    }
+}
+
+//BVH de los triangulos de una malla (un .obj cargado), en espacio de modelo
+pub struct MeshBvh {
+    triangles: Vec<[Vec3; 3]>,
+    bvh: Bvh,
+}
+
+impl MeshBvh {
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        let triangles: Vec<[Vec3; 3]> = mesh
+            .triangles()
+            .map(|triangle| triangle.map(|index| mesh.vertices[index as usize].position))
+            //los triangulos sin area no tienen punto mas cercano bien definido
+            .filter(|[a, b, c]| {
+                let (ab, ac) = (b - a, c - a);
+                ab.cross(&ac).norm() > 1e-6 * ab.norm() * ac.norm()
+            })
+            .collect();
+        let bounds: Vec<Aabb> = triangles.iter().map(Aabb::from_points).collect();
+        MeshBvh { bvh: Bvh::build(&bounds), triangles }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bvh.bounds()
+    }
+
+    //triangulo y distancia del primer impacto (las dos caras cuentan)
+    pub fn ray_cast(&self, ray: &Ray, max_t: f32) -> Option<(usize, f32)> {
+        self.bvh.ray_cast(ray, max_t, |i| ray_triangle(ray, &self.triangles[i]))
+    }
+
+    //triangulos a menos de `radius` de `center`
+    pub fn overlap_sphere(&self, center: &Vec3, radius: f32) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.bvh.query(
+            |bounds| bounds.overlaps_sphere(center, radius),
+            |i| {
+                if (self.closest_on(i, center) - center).norm_squared() < radius * radius {
+                    found.push(i);
+                }
+            },
+        );
+        found
+    }
+
+    pub fn closest_point(&self, point: &Vec3) -> Option<Vec3> {
+        let (i, _) = self.bvh.nearest(point, |i| (self.closest_on(i, point) - point).norm())?;
+        Some(self.closest_on(i, point))
+    }
+
+    fn closest_on(&self, i: usize, point: &Vec3) -> Vec3 {
+        let [a, b, c] = &self.triangles[i];
+        closest_point_on_triangle(point, a, b, c)
+    }
+}
+
+//Moller-Trumbore, sin descartar caras traseras
+fn ray_triangle(ray: &Ray, [a, b, c]: &[Vec3; 3]) -> Option<f32> {
+    let (ab, ac) = (b - a, c - a);
+    let p = ray.direction.cross(&ac);
+    let determinant = ab.dot(&p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let s = ray.origin - a;
+    let u = s.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&ab);
+    let v = ray.direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = ac.dot(&q) * inverse;
+    (t >= 0.0).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::Obj;
+
+    //numeros fijos sin depender de rand
+    fn points(count: usize) -> Vec<Vec3> {
+        let mut state = 12345u32;
+        let mut next = || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 20.0 - 10.0
+        };
+        (0..count).map(|_| Vec3::new(next(), next(), next())).collect()
+    }
+
+    #[test]
+    fn mesh_queries_match_brute_force() {
+        let mesh = MeshBvh::from_mesh(&Obj::load("assets/models/sphere.obj").unwrap().get_mesh());
+        let center = mesh.bounds().center();
+        let radius = mesh.triangles.iter().flatten().map(|p| (p - center).norm()).fold(0.0, f32::max);
+        for origin in points(40).iter().map(|p| center + p * radius * 0.3) {
+            let ray = Ray::new(origin, center - origin);
+            let brute = mesh.triangles.iter().filter_map(|t| ray_triangle(&ray, t)).fold(f32::INFINITY, f32::min);
+            let hit = mesh.ray_cast(&ray, f32::INFINITY).map_or(f32::INFINITY, |(_, t)| t);
+            assert!((hit - brute).abs() < 1e-3, "{} {}", hit, brute);
+            //el rayo llega a la superficie de la esfera
+            let surface = (ray.at(hit) - center).norm();
+            assert!(surface <= radius * 1.001 && surface > radius * 0.75, "{} {}", surface, radius);
+
+            let closest = mesh.closest_point(&origin).unwrap();
+            let brute = (0..mesh.triangles.len()).map(|i| (mesh.closest_on(i, &origin) - origin).norm()).fold(f32::INFINITY, f32::min);
+            assert!(((closest - origin).norm() - brute).abs() < 1e-3);
+        }
+        //solo los triangulos que tocan una esfera chica en el polo
+        let pole = Vec3::new(center.x, mesh.bounds().max.y, center.z);
+        let touching = mesh.overlap_sphere(&pole, radius * 0.2);
+        assert!(!touching.is_empty() && touching.len() < mesh.triangles.len() / 4);
+        let away = center + Vec3::repeat(radius * 2.0);
+        assert!(mesh.ray_cast(&Ray::new(away, Vec3::x()), f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn refit_follows_moving_primitives() {
+        let mut centers = points(100);
+        let boxes = |centers: &[Vec3]| centers.iter().map(|c| Aabb::around_sphere(c, 0.5)).collect::<Vec<_>>();
+        let mut bvh = Bvh::build(&boxes(&centers));
+        for step in 0..5 {
+            for (i, center) in centers.iter_mut().enumerate() {
+                center.x += (i % 7) as f32 * 0.3 * step as f32;
+            }
+            bvh.update(&boxes(&centers));
+
+            let probe = Aabb::around_sphere(&Vec3::new(2.0, 0.0, 0.0), 3.0);
+            let mut found = Vec::new();
+            bvh.query(|bounds| bounds.overlaps(&probe), |i| {
+                if boxes(&centers)[i].overlaps(&probe) {
+                    found.push(i);
+                }
+            });
+            found.sort();
+            let brute: Vec<usize> = (0..centers.len()).filter(|&i| boxes(&centers)[i].overlaps(&probe)).collect();
+            assert_eq!(found, brute);
+        }
+    }
+
+    #[test]
+    fn nearest_and_ray_over_spheres() {
+        let centers = points(64);
+        let bvh = Bvh::build(&centers.iter().map(|c| Aabb::around_sphere(c, 0.5)).collect::<Vec<_>>());
+        let distance = |i: usize, p: &Vec3| ((centers[i] - p).norm() - 0.5).max(0.0);
+        for point in points(10).iter().map(|p| p * 1.3) {
+            let (nearest, d) = bvh.nearest(&point, |i| distance(i, &point)).unwrap();
+            let brute = (0..centers.len()).min_by(|&a, &b| distance(a, &point).total_cmp(&distance(b, &point))).unwrap();
+            assert_eq!(nearest, brute);
+            assert_eq!(d, distance(brute, &point));
+        }
+
+        //el rayo hacia un centro no puede pegarle antes a otra esfera que un recorrido lineal
+        let ray = Ray::new(Vec3::new(-30.0, 0.0, 0.0), centers[0] - Vec3::new(-30.0, 0.0, 0.0));
+        let hit_sphere = |i: usize| {
+            let offset = ray.origin - centers[i];
+            let b = offset.dot(&ray.direction);
+            let c = offset.norm_squared() - 0.25;
+            let discriminant = b * b - c;
+            (discriminant >= 0.0).then(|| -b - discriminant.sqrt()).filter(|&t| t >= 0.0)
+        };
+        let brute = (0..centers.len()).filter_map(|i| hit_sphere(i).map(|t| (i, t))).min_by(|a, b| a.1.total_cmp(&b.1));
+        assert_eq!(bvh.ray_cast(&ray, 100.0, hit_sphere), brute);
+        assert!(bvh.ray_cast(&ray, 1.0, hit_sphere).is_none());
+    }
+}
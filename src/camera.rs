@@ -1,9 +1,19 @@
 use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
 
+//campo de vision vertical por defecto
+pub const DEFAULT_FOV: f32 = 45.0 * PI / 180.0;
+//planos de la proyeccion; el desenfoque los usa para sacar distancias del zbuffer
+pub const NEAR: f32 = 0.1;
+pub const FAR: f32 = 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Camera {
   pub eye: Vec3,
   pub center: Vec3,
-  pub up: Vec3
+  pub up: Vec3,
+  //en radianes
+  pub fov: f32
 }
 
 impl Camera {
@@ -11,11 +21,23 @@ impl Camera {
     let forward = (center - eye).normalize();  // Dirección hacia donde mira la cámara
     let right = forward.cross(&up).normalize(); // Dirección a la derecha
     let up = right.cross(&forward).normalize();
-    
+
     Camera {
       eye,
       center,
-      up
+      up,
+      fov: DEFAULT_FOV
     }
   }
-}
\ No newline at end of file
+
+  // Mezcla entre dos cámaras (t = 0 es esta, t = 1 la otra)
+  pub fn blend(&self, other: &Camera, t: f32) -> Camera {
+    let up = self.up.lerp(&other.up, t);
+    Camera {
+      eye: self.eye.lerp(&other.eye, t),
+      center: self.center.lerp(&other.center, t),
+      up: up.try_normalize(f32::EPSILON).unwrap_or(other.up),
+      fov: self.fov + (other.fov - self.fov) * t
+    }
+  }
+}
@@ -0,0 +1,435 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+
+use crate::camera::Camera;
+use crate::camera_track::CameraTrack;
+use crate::scene_graph::NodeId;
+use crate::spaceship::{ShipInput, Spaceship};
+
+//Controladores de camara. Cada modo calcula la camara que quiere y el rig
+//pasa de un modo a otro mezclando ambas durante `blend_seconds`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CameraMode {
+    //detras de la nave, con resorte
+    #[default]
+    Chase,
+    //alrededor del cuerpo elegido; los controles de la nave la giran y acercan
+    Orbit,
+    //camara libre para depurar; los controles de la nave la mueven
+    Free,
+    //desde la cabina
+    Cockpit,
+    //detras de la nave mirando al cuerpo elegido
+    Tracking,
+    //reproduce el recorrido grabado (`CameraTrack`) desde el inicio
+    Track,
+}
+
+pub const NAMES: &str = "chase, orbit, free, cockpit, tracking, track";
+
+const MODES: [CameraMode; 6] = [
+    CameraMode::Chase,
+    CameraMode::Orbit,
+    CameraMode::Free,
+    CameraMode::Cockpit,
+    CameraMode::Tracking,
+    CameraMode::Track,
+];
+
+impl CameraMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        MODES.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Chase => "chase",
+            CameraMode::Orbit => "orbit",
+            CameraMode::Free => "free",
+            CameraMode::Cockpit => "cockpit",
+            CameraMode::Tracking => "tracking",
+            CameraMode::Track => "track",
+        }
+    }
+
+    //en orbit y free los controles mueven la camara en vez de la nave
+    pub fn drives_ship(self) -> bool {
+        !matches!(self, CameraMode::Orbit | CameraMode::Free)
+    }
+
+    //los que miran un cuerpo en vez de la nave
+    pub fn needs_target(self) -> bool {
+        matches!(self, CameraMode::Orbit | CameraMode::Tracking)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RigSettings {
+    //posicion de la camara de persecucion respecto a la nave
+    pub chase_distance: f32,
+    pub chase_height: f32,
+    //que tan rapido el resorte alcanza su objetivo (1/s): mas alto es mas rigido
+    pub stiffness: f32,
+    pub blend_seconds: f32,
+    //apertura en grados; se abre hasta `fov + speed_fov` a toda velocidad
+    pub fov: f32,
+    pub speed_fov: f32,
+    //unidades/s de la camara libre (el boost la multiplica)
+    pub free_speed: f32,
+}
+
+impl Default for RigSettings {
+    fn default() -> Self {
+        RigSettings {
+            chase_distance: 10.0,
+            chase_height: 2.0,
+            stiffness: 5.0,
+            blend_seconds: 0.8,
+            fov: 45.0,
+            speed_fov: 20.0,
+            free_speed: 10.0,
+        }
+    }
+}
+
+//giro de las camaras libre y orbital, en rad/s
+const TURN_RATE: f32 = 1.5;
+const MAX_ELEVATION: f32 = 1.5;
+
+pub struct CameraRig {
+    mode: CameraMode,
+    settings: RigSettings,
+    //cuerpos que pueden mirar orbit y tracking
+    targets: Vec<(String, NodeId)>,
+    target: usize,
+    //recorrido grabado y segundos desde que empezo a reproducirse
+    track: Option<CameraTrack>,
+    track_time: f32,
+    //la ultima camara entregada
+    camera: Camera,
+    //camara de partida y avance (0 a 1) del cambio de modo
+    blend: Option<(Camera, f32)>,
+    started: bool,
+    //resorte de chase y tracking
+    eye: Vec3,
+    eye_velocity: Vec3,
+    up: Vec3,
+    fov: f32,
+    //orbit
+    azimuth: f32,
+    elevation: f32,
+    distance: f32,
+    //free
+    yaw: f32,
+    pitch: f32,
+}
+
+impl CameraRig {
+    pub fn new(mode: CameraMode, settings: RigSettings, targets: Vec<(String, NodeId)>, initial: Camera) -> Self {
+        CameraRig {
+            mode,
+            settings,
+            targets,
+            target: 0,
+            track: None,
+            track_time: 0.0,
+            camera: initial,
+            blend: None,
+            started: false,
+            eye: initial.eye,
+            eye_velocity: Vec3::zeros(),
+            up: initial.up,
+            fov: settings.fov.to_radians(),
+            azimuth: 0.0,
+            elevation: 0.0,
+            distance: 0.0,
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn target(&self) -> Option<NodeId> {
+        self.targets.get(self.target).map(|&(_, node)| node)
+    }
+
+    pub fn target_name(&self) -> Option<&str> {
+        self.targets.get(self.target).map(|(name, _)| name.as_str())
+    }
+
+    pub fn set_target(&mut self, name: &str) -> bool {
+        match self.targets.iter().position(|(target, _)| target == name) {
+            Some(index) => {
+                self.target = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_track(&mut self, track: CameraTrack) {
+        self.track = Some(track);
+    }
+
+    pub fn track(&self) -> Option<&CameraTrack> {
+        self.track.as_ref()
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.start_blend();
+        }
+    }
+
+    //salta `track` si no hay recorrido cargado
+    pub fn next_mode(&mut self) {
+        let index = MODES.iter().position(|&mode| mode == self.mode).unwrap_or(0);
+        let next = MODES[(index + 1) % MODES.len()];
+        self.set_mode(match next {
+            CameraMode::Track if self.track.is_none() => MODES[0],
+            _ => next,
+        });
+    }
+
+    pub fn next_target(&mut self) {
+        if !self.targets.is_empty() {
+            self.target = (self.target + 1) % self.targets.len();
+            if self.mode.needs_target() {
+                self.start_blend();
+            }
+        }
+    }
+
+    //parte de la camara actual; el estado del modo nuevo se toma de ella
+    fn start_blend(&mut self) {
+        self.blend = Some((self.camera, 0.0));
+        self.started = false;
+    }
+
+    //`target` es el centro y radio en mundo del cuerpo elegido
+    pub fn update(&mut self, ship: &Spaceship, target: Option<(Vec3, f32)>, input: &ShipInput, seconds: f32) -> Camera {
+        //sin cuerpo que mirar (o sin recorrido) se comportan como chase
+        let mode = match (self.mode.needs_target(), target) {
+            (true, None) => CameraMode::Chase,
+            _ if self.mode == CameraMode::Track && self.track.is_none() => CameraMode::Chase,
+            _ => self.mode,
+        };
+        let (center, radius) = target.unwrap_or((ship.position, 1.0));
+        if !self.started {
+            self.enter(mode, ship, center, radius);
+            self.started = true;
+        }
+
+        let desired = match mode {
+            CameraMode::Chase => self.chase(ship, center, seconds),
+            CameraMode::Tracking => {
+                self.follow(&self.goal(mode, ship, center), &ship.up(), seconds);
+                Camera { eye: self.eye, center, up: self.up, fov: 0.0 }
+            }
+            CameraMode::Cockpit => {
+                let eye = ship.position + ship.up() * 0.25 + ship.forward() * 0.3;
+                //el resorte sigue a la nave para salir sin saltos
+                self.eye = eye;
+                self.eye_velocity = ship.velocity;
+                self.up = ship.up();
+                Camera { eye, center: eye + ship.forward(), up: ship.up(), fov: 0.0 }
+            }
+            CameraMode::Orbit => {
+                self.azimuth += input.rotation.y * TURN_RATE * seconds;
+                self.elevation = (self.elevation + input.rotation.x * TURN_RATE * seconds)
+                    .clamp(-MAX_ELEVATION, MAX_ELEVATION);
+                self.distance = (self.distance * (-input.thrust.z * seconds).exp()).max(radius * 1.2);
+                let eye = center + direction(self.azimuth, self.elevation) * self.distance;
+                Camera { eye, center, up: Vec3::y(), fov: 0.0 }
+            }
+            CameraMode::Free => {
+                self.yaw += input.rotation.y * TURN_RATE * seconds;
+                self.pitch = (self.pitch + input.rotation.x * TURN_RATE * seconds).clamp(-MAX_ELEVATION, MAX_ELEVATION);
+                let forward = direction(self.yaw, self.pitch);
+                let right = forward.cross(&Vec3::y()).normalize();
+                let up = right.cross(&forward);
+                let boost = if input.boost { 4.0 } else { 1.0 };
+                let thrust = right * input.thrust.x + up * input.thrust.y + forward * input.thrust.z;
+                self.eye += thrust * self.settings.free_speed * boost * seconds;
+                Camera { eye: self.eye, center: self.eye + forward, up: Vec3::y(), fov: 0.0 }
+            }
+            CameraMode::Track => match &self.track {
+                //se muestra el instante actual y despues avanza: el frame n es n * seconds
+                Some(track) => {
+                    let camera = track.camera_at(self.track_time);
+                    self.track_time += seconds;
+                    camera
+                }
+                None => self.chase(ship, center, seconds),
+            },
+        };
+
+        //el recorrido trae su apertura; en los demas crece con la velocidad de la nave
+        let desired = if mode == CameraMode::Track {
+            self.fov = desired.fov;
+            desired
+        } else {
+            let speed = if mode.drives_ship() {
+                (ship.velocity.norm() / (ship.flight.max_speed * ship.flight.boost)).min(1.0)
+            } else {
+                0.0
+            };
+            let fov = (self.settings.fov + self.settings.speed_fov * speed).to_radians();
+            self.fov += (fov - self.fov) * smoothing(self.settings.stiffness, seconds);
+            Camera { fov: self.fov, ..desired }
+        };
+
+        self.camera = match self.blend.as_mut() {
+            Some((from, progress)) => {
+                *progress += seconds / self.settings.blend_seconds.max(f32::EPSILON);
+                let t = progress.min(1.0);
+                let camera = from.blend(&desired, t * t * (3.0 - 2.0 * t));
+                if *progress >= 1.0 {
+                    self.blend = None;
+                }
+                camera
+            }
+            None => desired,
+        };
+        self.camera
+    }
+
+    //estado inicial del modo a partir de donde esta la camara
+    fn enter(&mut self, mode: CameraMode, ship: &Spaceship, center: Vec3, radius: f32) {
+        let view = (self.camera.center - self.camera.eye).try_normalize(f32::EPSILON).unwrap_or(-Vec3::z());
+        match mode {
+            CameraMode::Chase | CameraMode::Tracking | CameraMode::Cockpit => {
+                //la primera vez arranca ya en su lugar
+                if self.blend.is_none() {
+                    self.eye = self.goal(mode, ship, center);
+                    self.up = ship.up();
+                } else {
+                    self.eye = self.camera.eye;
+                    self.up = self.camera.up;
+                }
+                self.eye_velocity = Vec3::zeros();
+            }
+            CameraMode::Orbit => {
+                let offset = (self.camera.eye - center).try_normalize(f32::EPSILON).unwrap_or(Vec3::z());
+                self.azimuth = offset.x.atan2(offset.z);
+                self.elevation = offset.y.asin().clamp(-MAX_ELEVATION, MAX_ELEVATION);
+                self.distance = radius * 4.0 + 1.0;
+            }
+            CameraMode::Free => {
+                self.eye = self.camera.eye;
+                self.yaw = view.x.atan2(view.z);
+                self.pitch = view.y.asin().clamp(-MAX_ELEVATION, MAX_ELEVATION);
+            }
+            CameraMode::Track => self.track_time = 0.0,
+        }
+    }
+
+    //detras de la nave mirando un poco por delante de la nariz
+    fn chase(&mut self, ship: &Spaceship, center: Vec3, seconds: f32) -> Camera {
+        self.follow(&self.goal(CameraMode::Chase, ship, center), &ship.up(), seconds);
+        Camera { eye: self.eye, center: ship.position + ship.forward() * 2.0, up: self.up, fov: 0.0 }
+    }
+
+    //a donde la lleva el resorte en chase y tracking
+    fn goal(&self, mode: CameraMode, ship: &Spaceship, center: Vec3) -> Vec3 {
+        let behind = match mode {
+            //la nave queda entre la camara y el cuerpo
+            CameraMode::Tracking => (ship.position - center).try_normalize(f32::EPSILON).unwrap_or(-ship.forward()),
+            _ => -ship.forward(),
+        };
+        ship.position + behind * self.settings.chase_distance + ship.up() * self.settings.chase_height
+    }
+
+    //resorte criticamente amortiguado (no se pasa del objetivo)
+    fn follow(&mut self, goal: &Vec3, up: &Vec3, seconds: f32) {
+        let omega = self.settings.stiffness;
+        let offset = self.eye - goal;
+        let decay = (-omega * seconds).exp();
+        let temp = (self.eye_velocity + offset * omega) * seconds;
+        self.eye_velocity = (self.eye_velocity - temp * omega) * decay;
+        self.eye = goal + (offset + temp) * decay;
+        let up = self.up.lerp(up, smoothing(omega, seconds));
+        self.up = up.try_normalize(f32::EPSILON).unwrap_or(Vec3::y());
+    }
+}
+
+//fraccion que se acerca al objetivo en `seconds`, sin depender de los fps
+fn smoothing(rate: f32, seconds: f32) -> f32 {
+    1.0 - (-rate * seconds).exp()
+}
+
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rig(mode: CameraMode) -> CameraRig {
+        let initial = Camera::new(Vec3::new(0.0, 5.0, 20.0), Vec3::zeros(), Vec3::y());
+        CameraRig::new(mode, RigSettings::default(), vec![("sun".to_string(), 0)], initial)
+    }
+
+    #[test]
+    fn chase_spring_settles_behind_without_overshoot() {
+        let mut ship = Spaceship::new(Vec3::zeros());
+        let mut rig = rig(CameraMode::Chase);
+        let camera = rig.update(&ship, None, &ShipInput::default(), 1.0 / 60.0);
+        //arranca ya detras de la nave (la nariz mira a -z)
+        assert!((camera.eye - Vec3::new(0.0, 2.0, 10.0)).norm() < 1e-3, "{}", camera.eye);
+
+        //la nave salta 5 unidades: la camara la alcanza de a poco y sin pasarse
+        ship.position = Vec3::new(0.0, 0.0, -5.0);
+        let mut previous = camera.eye.z;
+        for _ in 0..240 {
+            let eye = rig.update(&ship, None, &ShipInput::default(), 1.0 / 60.0).eye;
+            assert!(eye.z <= previous + 1e-5 && eye.z >= 5.0 - 1e-3, "{}", eye.z);
+            previous = eye.z;
+        }
+        assert!((previous - 5.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn switching_modes_blends() {
+        let ship = Spaceship::new(Vec3::zeros());
+        let body = Some((Vec3::new(50.0, 0.0, 0.0), 2.0));
+        let mut rig = rig(CameraMode::Chase);
+        let chase = rig.update(&ship, body, &ShipInput::default(), 0.1);
+
+        rig.set_mode(CameraMode::Orbit);
+        let first = rig.update(&ship, body, &ShipInput::default(), 0.01);
+        assert!((first.center - chase.center).norm() < 1.0, "{}", first.center);
+        for _ in 0..20 {
+            rig.update(&ship, body, &ShipInput::default(), 0.1);
+        }
+        //ya termino: mira al cuerpo desde la distancia de orbita
+        let orbit = rig.camera();
+        assert_eq!(orbit.center, Vec3::new(50.0, 0.0, 0.0));
+        assert!(((orbit.eye - orbit.center).norm() - 9.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fov_widens_with_speed() {
+        let mut ship = Spaceship::new(Vec3::zeros());
+        let mut rig = rig(CameraMode::Cockpit);
+        let still = rig.update(&ship, None, &ShipInput::default(), 0.1).fov;
+        assert!((still - 45f32.to_radians()).abs() < 1e-4);
+        ship.velocity = Vec3::new(0.0, 0.0, -ship.flight.max_speed * ship.flight.boost);
+        for _ in 0..60 {
+            rig.update(&ship, None, &ShipInput::default(), 0.1);
+        }
+        assert!((rig.camera().fov - 65f32.to_radians()).abs() < 1e-3);
+    }
+}
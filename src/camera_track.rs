@@ -0,0 +1,299 @@
+use nalgebra::SVector;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::fmt;
+
+use crate::camera::Camera;
+
+//Recorridos de camara grabados en un archivo TOML: claves con tiempo, ojo,
+//punto al que mira, arriba y apertura, unidas con splines. Con `--headless`
+//cada frame cae siempre en el mismo punto del recorrido.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    Linear,
+    //pasa por todas las claves con velocidad continua
+    #[default]
+    CatmullRom,
+    //las claves sin `time` son puntos de control del tramo (una o dos)
+    Bezier,
+}
+
+//como se reparte el tiempo dentro de cada tramo
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrackDescription {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    //easing de todos los tramos, salvo los que digan otro en su clave
+    #[serde(default)]
+    pub easing: Easing,
+    //al terminar vuelve a empezar (para no saltar, la ultima clave repite la primera)
+    #[serde(default)]
+    pub looping: bool,
+    pub keyframes: Vec<KeyframeDescription>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyframeDescription {
+    //segundos desde el inicio; solo lo omiten los puntos de control de bezier
+    pub time: Option<f32>,
+    pub eye: [f32; 3],
+    pub center: [f32; 3],
+    pub up: Option<[f32; 3]>,
+    //grados; si falta se mantiene el de la clave anterior
+    pub fov: Option<f32>,
+    //easing del tramo que empieza en esta clave
+    pub easing: Option<Easing>,
+}
+
+#[derive(Debug)]
+pub enum TrackError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for TrackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrackError::Io(err) => write!(f, "{}", err),
+            TrackError::Parse(err) => write!(f, "{}", err),
+            TrackError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+//ojo, centro, arriba y apertura en un solo vector para interpolar todo junto
+type Pose = SVector<f32, 10>;
+
+struct Segment {
+    start: f32,
+    end: f32,
+    easing: Easing,
+    //curva cubica de bezier; catmull-rom y lineal tambien se guardan asi
+    points: [Pose; 4],
+}
+
+pub struct CameraTrack {
+    segments: Vec<Segment>,
+    //pose fija cuando hay una sola clave
+    first: Pose,
+    looping: bool,
+}
+
+impl CameraTrack {
+    pub fn load(path: &str) -> Result<Self, TrackError> {
+        let source = std::fs::read_to_string(path).map_err(TrackError::Io)?;
+        let description: TrackDescription = toml::from_str(&source).map_err(TrackError::Parse)?;
+        Self::build(&description)
+    }
+
+    pub fn build(description: &TrackDescription) -> Result<Self, TrackError> {
+        let invalid = |msg: &str| TrackError::Invalid(msg.to_string());
+        if description.keyframes.is_empty() {
+            return Err(invalid("no keyframes"));
+        }
+
+        //anclas (con tiempo) y los puntos de control que les siguen
+        let mut fov = crate::camera::DEFAULT_FOV.to_degrees();
+        let mut anchors: Vec<(f32, Pose, Easing, Vec<Pose>)> = Vec::new();
+        for keyframe in &description.keyframes {
+            fov = keyframe.fov.unwrap_or(fov);
+            if !(fov > 0.0 && fov < 180.0) {
+                return Err(invalid("fov must be between 0 and 180 degrees"));
+            }
+            let pose = pose(keyframe, fov);
+            if pose.iter().any(|value| !value.is_finite()) {
+                return Err(invalid("keyframe values must be finite"));
+            }
+            match (keyframe.time, anchors.last_mut()) {
+                (Some(time), last) => {
+                    if last.is_some_and(|(previous, ..)| time <= *previous) || !time.is_finite() {
+                        return Err(invalid("keyframe times must increase"));
+                    }
+                    anchors.push((time, pose, keyframe.easing.unwrap_or(description.easing), Vec::new()));
+                }
+                (None, Some((_, _, _, controls))) if description.interpolation == Interpolation::Bezier => {
+                    controls.push(pose);
+                }
+                (None, _) => return Err(invalid("every keyframe needs a time (except bezier control points)")),
+            }
+        }
+        if anchors.last().is_some_and(|(_, _, _, controls)| !controls.is_empty()) {
+            return Err(invalid("the last bezier keyframe needs a time"));
+        }
+
+        //pendiente de catmull-rom en cada ancla, con tiempos no uniformes
+        let slope = |i: usize| {
+            let (before, after) = (i.saturating_sub(1), (i + 1).min(anchors.len() - 1));
+            (anchors[after].1 - anchors[before].1) / (anchors[after].0 - anchors[before].0)
+        };
+        let mut segments = Vec::new();
+        for i in 0..anchors.len().saturating_sub(1) {
+            let ((start, from, easing, controls), (end, to, ..)) = (&anchors[i], &anchors[i + 1]);
+            let duration = end - start;
+            let (a, b) = match (description.interpolation, controls.as_slice()) {
+                (Interpolation::CatmullRom, _) => {
+                    (from + slope(i) * (duration / 3.0), to - slope(i + 1) * (duration / 3.0))
+                }
+                (Interpolation::Linear, _) | (Interpolation::Bezier, []) => {
+                    (from.lerp(to, 1.0 / 3.0), from.lerp(to, 2.0 / 3.0))
+                }
+                //cuadratica elevada a cubica
+                (Interpolation::Bezier, [control]) => {
+                    (from.lerp(control, 2.0 / 3.0), to.lerp(control, 2.0 / 3.0))
+                }
+                (Interpolation::Bezier, [a, b]) => (*a, *b),
+                (Interpolation::Bezier, _) => return Err(invalid("at most two bezier control points per segment")),
+            };
+            segments.push(Segment { start: *start, end: *end, easing: *easing, points: [*from, a, b, *to] });
+        }
+
+        Ok(CameraTrack { segments, first: anchors[0].1, looping: description.looping })
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.segments.last().map_or(0.0, |segment| segment.end)
+    }
+
+    //camara en `time` segundos desde el inicio
+    pub fn camera_at(&self, time: f32) -> Camera {
+        let (Some(first), Some(last)) = (self.segments.first(), self.segments.last()) else {
+            return camera(&self.first);
+        };
+        let time = if self.looping && time > last.end {
+            first.start + (time - first.start).rem_euclid(last.end - first.start)
+        } else {
+            time.clamp(first.start, last.end)
+        };
+        let index = self.segments.partition_point(|segment| segment.end < time).min(self.segments.len() - 1);
+        let segment = &self.segments[index];
+        let t = segment.easing.apply(((time - segment.start) / (segment.end - segment.start)).clamp(0.0, 1.0));
+        let [p0, p1, p2, p3] = &segment.points;
+        let s = 1.0 - t;
+        camera(&(p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)))
+    }
+}
+
+fn pose(keyframe: &KeyframeDescription, fov: f32) -> Pose {
+    let [ex, ey, ez] = keyframe.eye;
+    let [cx, cy, cz] = keyframe.center;
+    let [ux, uy, uz] = keyframe.up.unwrap_or([0.0, 1.0, 0.0]);
+    Pose::from_column_slice(&[ex, ey, ez, cx, cy, cz, ux, uy, uz, fov])
+}
+
+fn camera(pose: &Pose) -> Camera {
+    let up = Vec3::new(pose[6], pose[7], pose[8]);
+    Camera {
+        eye: Vec3::new(pose[0], pose[1], pose[2]),
+        center: Vec3::new(pose[3], pose[4], pose[5]),
+        up: up.try_normalize(f32::EPSILON).unwrap_or(Vec3::y()),
+        fov: pose[9].to_radians(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(source: &str) -> Result<CameraTrack, TrackError> {
+        CameraTrack::build(&toml::from_str(source).unwrap())
+    }
+
+    const FLYBY: &str = r#"
+        [[keyframes]]
+        time = 0.0
+        eye = [0.0, 0.0, 0.0]
+        center = [0.0, 0.0, -1.0]
+        [[keyframes]]
+        time = 1.0
+        eye = [4.0, 0.0, 0.0]
+        center = [0.0, 0.0, -1.0]
+        fov = 60.0
+        [[keyframes]]
+        time = 3.0
+        eye = [4.0, 4.0, 0.0]
+        center = [0.0, 0.0, -1.0]
+    "#;
+
+    #[test]
+    fn catmull_rom_passes_through_keyframes_smoothly() {
+        let track = track(FLYBY).unwrap();
+        assert_eq!(track.duration(), 3.0);
+        assert_eq!(track.camera_at(1.0).eye, Vec3::new(4.0, 0.0, 0.0));
+        assert!((track.camera_at(1.0).fov - 60f32.to_radians()).abs() < 1e-6);
+        //la ultima clave hereda la apertura de la anterior; fuera del rango se queda en los bordes
+        assert_eq!(track.camera_at(10.0).eye, Vec3::new(4.0, 4.0, 0.0));
+        assert!((track.camera_at(10.0).fov - 60f32.to_radians()).abs() < 1e-6);
+
+        //la velocidad no salta al cruzar una clave
+        let h = 1e-3;
+        let before = (track.camera_at(1.0).eye - track.camera_at(1.0 - h).eye) / h;
+        let after = (track.camera_at(1.0 + h).eye - track.camera_at(1.0).eye) / h;
+        assert!((before - after).norm() < 0.05, "{} {}", before, after);
+    }
+
+    #[test]
+    fn easing_and_looping() {
+        let eased = track(&format!("easing = \"ease_in_out\"\nlooping = true\n{}", FLYBY)).unwrap();
+        let linear = track(FLYBY).unwrap();
+        //arranca mas lento pero llega igual
+        assert!(eased.camera_at(0.1).eye.x < linear.camera_at(0.1).eye.x);
+        assert_eq!(eased.camera_at(1.0).eye, linear.camera_at(1.0).eye);
+        assert!((eased.camera_at(3.5).eye - eased.camera_at(0.5).eye).norm() < 1e-4);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+    }
+
+    #[test]
+    fn bezier_control_points() {
+        let curve = track(
+            r#"
+            interpolation = "bezier"
+            [[keyframes]]
+            time = 0.0
+            eye = [0.0, 0.0, 0.0]
+            center = [0.0, 0.0, -1.0]
+            [[keyframes]]
+            eye = [0.0, 4.0, 0.0]
+            center = [0.0, 0.0, -1.0]
+            [[keyframes]]
+            eye = [4.0, 4.0, 0.0]
+            center = [0.0, 0.0, -1.0]
+            [[keyframes]]
+            time = 2.0
+            eye = [4.0, 0.0, 0.0]
+            center = [0.0, 0.0, -1.0]
+        "#,
+        )
+        .unwrap();
+        assert!((curve.camera_at(1.0).eye - Vec3::new(2.0, 3.0, 0.0)).norm() < 1e-5);
+
+        //sin tiempo solo se permite en bezier, y los tiempos deben crecer
+        assert!(track(&FLYBY.replace("time = 1.0", "")).is_err());
+        assert!(track(&FLYBY.replace("time = 3.0", "time = 0.5")).is_err());
+    }
+}
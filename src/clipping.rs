@@ -0,0 +1,132 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use crate::vertex::Vertex;
+
+//Recorte en espacio de recorte (coordenadas homogeneas), antes de dividir
+//por w. Un plano deja pasar los puntos con distancia >= 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipPlane {
+    //z >= -w
+    Near,
+    //z <= w
+    Far,
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl ClipPlane {
+    fn distance(self, position: &Vec4) -> f32 {
+        match self {
+            ClipPlane::Near => position.z + position.w,
+            ClipPlane::Far => position.w - position.z,
+            ClipPlane::Left => position.x + position.w,
+            ClipPlane::Right => position.w - position.x,
+            ClipPlane::Bottom => position.y + position.w,
+            ClipPlane::Top => position.w - position.y,
+        }
+    }
+}
+
+//near primero: despues de el w > 0 y los demas planos son seguros
+const PLANES: [ClipPlane; 6] = [
+    ClipPlane::Near,
+    ClipPlane::Far,
+    ClipPlane::Left,
+    ClipPlane::Right,
+    ClipPlane::Bottom,
+    ClipPlane::Top,
+];
+
+pub enum Clipped {
+    //entero adentro: se dibuja con sus vertices originales
+    Inside,
+    Outside,
+    //poligono convexo con vertices nuevos, se dibuja en abanico
+    Polygon(Vec<Vertex>),
+}
+
+//Recorta un triangulo contra el frustum
+pub fn clip_triangle(triangle: [&Vertex; 3]) -> Clipped {
+    let inside_all = PLANES
+        .iter()
+        .all(|plane| triangle.iter().all(|vertex| plane.distance(&vertex.clip_position) >= 0.0));
+    if inside_all {
+        return Clipped::Inside;
+    }
+
+    let mut polygon: Vec<Vertex> = triangle.into_iter().cloned().collect();
+    for plane in PLANES {
+        polygon = clip_polygon(&polygon, plane);
+        if polygon.len() < 3 {
+            return Clipped::Outside;
+        }
+    }
+    Clipped::Polygon(polygon)
+}
+
+//Sutherland-Hodgman contra un plano
+fn clip_polygon(polygon: &[Vertex], plane: ClipPlane) -> Vec<Vertex> {
+    let mut output = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let current_distance = plane.distance(&current.clip_position);
+        let next_distance = plane.distance(&next.clip_position);
+
+        if current_distance >= 0.0 {
+            output.push(current.clone());
+        }
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            output.push(current.lerp(next, t));
+        }
+    }
+    output
+}
+
+//division por w y viewport: deja la posicion de pantalla en `transformed_position`
+pub fn to_screen(vertex: &mut Vertex, viewport_matrix: &Mat4) {
+    let clip = vertex.clip_position;
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = viewport_matrix * ndc;
+    vertex.transformed_position = Vec3::new(screen.x, screen.y, screen.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32, y: f32, z: f32, w: f32) -> Vertex {
+        Vertex {
+            clip_position: Vec4::new(x, y, z, w),
+            ..Vertex::default()
+        }
+    }
+
+    fn clip(v1: Vertex, v2: Vertex, v3: Vertex) -> Clipped {
+        clip_triangle([&v1, &v2, &v3])
+    }
+
+    #[test]
+    fn keeps_visible_triangle() {
+        let clipped = clip(vertex(0.0, 0.0, 0.0, 1.0), vertex(1.0, 0.0, 0.0, 1.0), vertex(0.0, 1.0, 0.5, 1.0));
+        assert!(matches!(clipped, Clipped::Inside));
+    }
+
+    #[test]
+    fn drops_triangle_behind_camera() {
+        let clipped = clip(vertex(0.0, 0.0, -2.0, 1.0), vertex(1.0, 0.0, -3.0, 1.0), vertex(0.0, 1.0, -2.5, 1.0));
+        assert!(matches!(clipped, Clipped::Outside));
+    }
+
+    #[test]
+    fn splits_triangle_crossing_near_plane() {
+        let clipped = clip(vertex(0.0, 0.0, -3.0, 1.0), vertex(1.0, 0.0, 0.0, 1.0), vertex(0.0, 1.0, 0.0, 1.0));
+        let Clipped::Polygon(polygon) = clipped else { panic!("expected a clipped polygon") };
+        assert_eq!(polygon.len(), 4);
+        for corner in &polygon {
+            assert!(ClipPlane::Near.distance(&corner.clip_position) >= -1e-6);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Instant;
+
+//un frame muy lento (breakpoint, ventana arrastrada) no debe saltar la simulacion
+const MAX_FRAME_SECONDS: f32 = 0.25;
+
+//Reloj de simulacion: tiempo real entre frames escalado por `time_scale`.
+//Una escala negativa corre la simulacion hacia atras.
+pub struct SimulationClock {
+    time_scale: f32,
+    paused: bool,
+    elapsed: f64,
+    last_tick: Option<Instant>,
+}
+
+impl SimulationClock {
+    pub fn new() -> Self {
+        SimulationClock {
+            time_scale: 1.0,
+            paused: false,
+            elapsed: 0.0,
+            last_tick: None,
+        }
+    }
+
+    //mide el tiempo real desde el tick anterior; devuelve (real, simulado)
+    pub fn tick(&mut self) -> (f32, f32) {
+        let now = Instant::now();
+        let real = self
+            .last_tick
+            .map(|last| now.duration_since(last).as_secs_f32())
+            .unwrap_or(0.0);
+        self.last_tick = Some(now);
+        let real = real.min(MAX_FRAME_SECONDS);
+        (real, self.advance(real))
+    }
+
+    //avanza un tiempo real dado (render sin ventana); devuelve el simulado
+    pub fn advance(&mut self, real_seconds: f32) -> f32 {
+        let simulated = if self.paused { 0.0 } else { real_seconds * self.time_scale };
+        self.elapsed += simulated as f64;
+        simulated
+    }
+
+    //segundos simulados desde el inicio
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed as f32
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale;
+    }
+
+    pub fn reverse(&mut self) {
+        self.time_scale = -self.time_scale;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        SimulationClock::new()
+    }
+}
+
+//limite de pasos por frame para no quedar atrapado si la escala es enorme
+const MAX_STEPS_PER_FRAME: u32 = 2000;
+
+//Acumulador de paso fijo: convierte tiempo variable en pasos enteros
+pub struct FixedStep {
+    pub step: f64,
+    accumulator: f64,
+}
+
+impl FixedStep {
+    pub fn new(step: f64) -> Self {
+        FixedStep { step, accumulator: 0.0 }
+    }
+
+    //pasos a dar este frame; negativos si el tiempo va hacia atras
+    pub fn accumulate(&mut self, seconds: f64) -> i32 {
+        self.accumulator += seconds;
+        let steps = (self.accumulator / self.step).trunc();
+        let clamped = steps.clamp(-(MAX_STEPS_PER_FRAME as f64), MAX_STEPS_PER_FRAME as f64);
+        if steps == clamped {
+            self.accumulator -= steps * self.step;
+        } else {
+            self.accumulator = 0.0;
+        }
+        clamped as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_and_scale() {
+        let mut clock = SimulationClock::new();
+        assert_eq!(clock.advance(0.5), 0.5);
+        clock.set_time_scale(10.0);
+        assert_eq!(clock.advance(0.5), 5.0);
+        clock.toggle_pause();
+        assert_eq!(clock.advance(0.5), 0.0);
+        clock.toggle_pause();
+        clock.reverse();
+        assert_eq!(clock.advance(0.25), -2.5);
+        assert_eq!(clock.elapsed(), 3.0);
+    }
+
+    #[test]
+    fn fixed_step_keeps_remainder() {
+        let mut fixed = FixedStep::new(0.25);
+        assert_eq!(fixed.accumulate(0.625), 2);
+        assert_eq!(fixed.accumulate(0.125), 1);
+        assert_eq!(fixed.accumulate(0.0), 0);
+        assert_eq!(fixed.accumulate(-0.5), -2);
+    }
+}
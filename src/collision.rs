@@ -0,0 +1,340 @@
+use nalgebra_glm::{Mat4, Vec3};
+use serde::Deserialize;
+
+use crate::scene_graph::{Model, NodeId};
+
+//Choques de la nave contra los cuerpos. Cada cuerpo es una esfera que sigue
+//su escala; la nave usa los triangulos de su malla. El movimiento se barre
+//entero (no solo la posicion final) para no atravesar nada a alta velocidad.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionShape {
+    //esfera envolvente del modelo, escalada
+    #[default]
+    Sphere,
+    //sin choques (anillos, efectos)
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Response {
+    //pierde la velocidad contra la superficie y sigue de costado
+    #[default]
+    Slide,
+    //rebota con `restitution`
+    Bounce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollisionSettings {
+    pub response: Response,
+    //fraccion de la velocidad contra la superficie que devuelve el rebote
+    pub restitution: f32,
+}
+
+impl Default for CollisionSettings {
+    fn default() -> Self {
+        CollisionSettings { response: Response::Slide, restitution: 0.5 }
+    }
+}
+
+//cuerpo con el que se puede chocar, ya en mundo. `center` es donde queda al
+//final del movimiento y `motion` cuanto se movio durante el (los planetas
+//avanzan su orbita mientras la nave se mueve)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub node: NodeId,
+    pub center: Vec3,
+    pub radius: f32,
+    pub motion: Vec3,
+    pub velocity: Vec3,
+}
+
+impl Collider {
+    //el cuerpo cuando ya paso la fraccion `progress` del movimiento
+    fn at(&self, progress: f32) -> Collider {
+        Collider { center: self.center - self.motion * (1.0 - progress), ..*self }
+    }
+}
+
+//lo que ve el juego de cada choque
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionEvent {
+    pub node: NodeId,
+    //punto de contacto sobre la nave y normal hacia afuera del cuerpo
+    pub point: Vec3,
+    pub normal: Vec3,
+    //velocidad de impacto contra la superficie
+    pub speed: f32,
+}
+
+//separacion que se deja despues de un choque
+const SKIN: f32 = 1e-3;
+//pasadas de choque por movimiento (deslizar puede tocar otro cuerpo)
+const MAX_PASSES: usize = 3;
+
+//casco de la nave en mundo, en la posicion de partida del movimiento. Las
+//consultas van a la BVH del modelo; la matriz debe ser rigida (la nave no se
+//escala) para que las distancias no cambien.
+pub struct Hull<'a> {
+    model: &'a Model,
+    matrix: Mat4,
+    inverse: Mat4,
+    center: Vec3,
+    radius: f32,
+}
+
+impl<'a> Hull<'a> {
+    pub fn new(model: &'a Model, matrix: Mat4) -> Self {
+        let inverse = matrix.try_inverse().unwrap_or_else(Mat4::identity);
+        let center = (matrix * model.center.push(1.0)).xyz();
+        Hull { model, matrix, inverse, center, radius: model.radius }
+    }
+
+    //punto del casco (desplazado `offset`) mas cercano a `point`
+    fn closest_point(&self, offset: &Vec3, point: &Vec3) -> Vec3 {
+        let local = (self.inverse * (point - offset).push(1.0)).xyz();
+        match self.model.bvh.closest_point(&local) {
+            Some(closest) => (self.matrix * closest.push(1.0)).xyz() + offset,
+            None => self.center + offset,
+        }
+    }
+
+    fn penetrates(&self, offset: &Vec3, collider: &Collider) -> bool {
+        let local = (self.inverse * (collider.center - offset).push(1.0)).xyz();
+        !self.model.bvh.overlap_sphere(&local, collider.radius).is_empty()
+    }
+
+    //primer instante (0 a 1) en que el casco, desplazado `offset` y
+    //moviendose `motion`, toca la esfera
+    fn sweep(&self, offset: &Vec3, motion: &Vec3, collider: &Collider) -> Option<f32> {
+        //primero las esferas envolventes, de forma exacta
+        let center = self.center + offset;
+        let (enter, exit) = sweep_spheres(&center, self.radius, motion, &collider.center, collider.radius)?;
+        let (start, end) = (enter.max(0.0), exit.min(1.0));
+        if start > end {
+            return None;
+        }
+        //despues el casco, en pasos mas chicos que la esfera y que la nave
+        let length = motion.norm() * (end - start);
+        let step = (collider.radius.min(self.radius) * 0.25).max(1e-3);
+        let steps = ((length / step).ceil() as usize).max(1);
+        let mut previous = start;
+        for i in 0..=steps {
+            let t = start + (end - start) * i as f32 / steps as f32;
+            if self.penetrates(&(offset + motion * t), collider) {
+                if i == 0 {
+                    return Some(t);
+                }
+                //biseccion entre el ultimo paso libre y este
+                let (mut free, mut hit) = (previous, t);
+                for _ in 0..12 {
+                    let middle = (free + hit) / 2.0;
+                    if self.penetrates(&(offset + motion * middle), collider) {
+                        hit = middle;
+                    } else {
+                        free = middle;
+                    }
+                }
+                return Some(free);
+            }
+            previous = t;
+        }
+        None
+    }
+}
+
+//Mueve la nave `motion` (posicion y velocidad se corrigen) y devuelve los
+//choques. Cada cuerpo se barre en su propio marco: a la nave se le resta lo
+//que se mueve el cuerpo, asi un planeta que la alcanza tambien choca y la empuja
+pub fn move_hull(
+    hull: &Hull,
+    position: &mut Vec3,
+    velocity: &mut Vec3,
+    motion: Vec3,
+    colliders: &[Collider],
+    settings: &CollisionSettings,
+) -> Vec<CollisionEvent> {
+    let mut events = Vec::new();
+    let start = *position;
+    let mut remaining = motion;
+    //fraccion del movimiento ya hecha, para saber donde van los cuerpos
+    let mut progress = 0.0;
+    for _ in 0..MAX_PASSES {
+        //el choque mas temprano de este tramo
+        let offset = *position - start;
+        let hit = colliders
+            .iter()
+            .filter_map(|collider| {
+                let relative = remaining - collider.motion * (1.0 - progress);
+                hull.sweep(&offset, &relative, &collider.at(progress)).map(|t| (t, collider))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        let Some((t, collider)) = hit else {
+            *position += remaining;
+            break;
+        };
+
+        *position += remaining * t;
+        progress += (1.0 - progress) * t;
+        let body = collider.at(progress);
+        let offset = *position - start;
+        let point = hull.closest_point(&offset, &body.center);
+        let to_point = point - body.center;
+        let normal = to_point.try_normalize(f32::EPSILON).unwrap_or_else(|| {
+            (hull.center + offset - body.center).try_normalize(f32::EPSILON).unwrap_or(Vec3::y())
+        });
+        //si ya estaba adentro, sale por la normal
+        *position += normal * ((body.radius - to_point.norm()).max(0.0) + SKIN);
+
+        let relative = *velocity - body.velocity;
+        let approach = relative.dot(&normal);
+        let bounce = match settings.response {
+            Response::Slide => 1.0,
+            Response::Bounce => 1.0 + settings.restitution,
+        };
+        if approach < 0.0 {
+            *velocity -= normal * (approach * bounce);
+            events.push(CollisionEvent { node: body.node, point, normal, speed: -approach });
+        }
+        //lo que falta del movimiento, visto desde el cuerpo, sigue la misma
+        //regla que la velocidad; si el cuerpo viene hacia la nave la arrastra
+        remaining *= 1.0 - t;
+        let carried = body.motion * (1.0 - progress);
+        let mut relative = remaining - carried;
+        let into = relative.dot(&normal);
+        if into < 0.0 {
+            relative -= normal * (into * bounce);
+        }
+        remaining = relative + carried;
+    }
+    events
+}
+
+//intervalo (puede empezar antes de 0) en que dos esferas se tocan si la
+//primera se mueve `motion`
+fn sweep_spheres(center: &Vec3, radius: f32, motion: &Vec3, other: &Vec3, other_radius: f32) -> Option<(f32, f32)> {
+    let offset = center - other;
+    let reach = radius + other_radius;
+    let a = motion.norm_squared();
+    let b = 2.0 * offset.dot(motion);
+    let c = offset.norm_squared() - reach * reach;
+    if a <= f32::EPSILON {
+        return (c <= 0.0).then_some((0.0, 1.0));
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    Some(((-b - root) / (2.0 * a), (-b + root) / (2.0 * a)))
+}
+
+//punto del triangulo mas cercano a `p` (Ericson, Real-Time Collision Detection)
+pub fn closest_point_on_triangle(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3) -> Vec3 {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use crate::vertex::Vertex;
+
+    //tetraedro chico centrado en el origen
+    fn tetrahedron() -> Model {
+        let corners = [Vec3::new(0.2, 0.0, 0.0), Vec3::new(-0.2, 0.0, 0.1), Vec3::new(0.0, 0.2, -0.1), Vec3::new(0.0, -0.2, -0.1)];
+        let mesh = Mesh {
+            vertices: corners.iter().map(|p| Vertex::new(*p, Vec3::y(), nalgebra_glm::Vec2::zeros())).collect(),
+            indices: vec![0, 1, 2, 0, 3, 1, 0, 2, 3, 1, 3, 2],
+        };
+        Model::new(mesh)
+    }
+
+    fn hull(model: &Model, position: Vec3) -> Hull<'_> {
+        Hull::new(model, nalgebra_glm::translation(&position))
+    }
+
+    fn sphere(center: Vec3, radius: f32) -> Collider {
+        Collider { node: 7, center, radius, motion: Vec3::zeros(), velocity: Vec3::zeros() }
+    }
+
+    #[test]
+    fn closest_point_regions() {
+        let (a, b, c) = (Vec3::zeros(), Vec3::x(), Vec3::y());
+        assert!((closest_point_on_triangle(&Vec3::new(0.2, 0.2, 5.0), &a, &b, &c) - Vec3::new(0.2, 0.2, 0.0)).norm() < 1e-6);
+        assert_eq!(closest_point_on_triangle(&Vec3::new(-1.0, -1.0, 0.0), &a, &b, &c), a);
+        assert_eq!(closest_point_on_triangle(&Vec3::new(0.5, -2.0, 1.0), &a, &b, &c), Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(closest_point_on_triangle(&Vec3::new(1.0, 1.0, 0.0), &a, &b, &c), Vec3::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn fast_ship_does_not_tunnel() {
+        let model = tetrahedron();
+        //100 unidades en un paso, a traves de una esfera de radio 1
+        let (mut position, mut velocity) = (Vec3::zeros(), Vec3::new(0.0, 0.0, -6000.0));
+        let planet = sphere(Vec3::new(0.0, 0.0, -50.0), 1.0);
+        let events = move_hull(&hull(&model, position), &mut position, &mut velocity, Vec3::new(0.0, 0.0, -100.0), &[planet], &CollisionSettings::default());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].node, 7);
+        assert!((events[0].normal - Vec3::z()).norm() < 1e-3);
+        //queda frente a la superficie, sin velocidad contra ella
+        assert!(position.z > -49.0 && position.z < -48.7, "{}", position);
+        assert!(velocity.norm() < 1e-3);
+    }
+
+    #[test]
+    fn slide_and_bounce() {
+        let model = tetrahedron();
+        let planet = sphere(Vec3::new(0.0, -1.0, 0.0), 1.0);
+        let motion = Vec3::new(1.0, -1.0, 0.0);
+        let slide = CollisionSettings::default();
+        let start = Vec3::new(0.0, 0.3, 0.0);
+        let (mut position, mut velocity) = (start, Vec3::new(1.0, -1.0, 0.0));
+        move_hull(&hull(&model, start), &mut position, &mut velocity, motion, &[planet], &slide);
+        //sigue de costado y no entra
+        assert!(velocity.x > 0.5 && velocity.y > -0.5, "{}", velocity);
+        assert!(position.x > 0.3);
+        assert!(!hull(&model, start).penetrates(&(position - start), &planet));
+
+        //justo debajo del vertice mas bajo del casco
+        let planet = sphere(Vec3::new(0.0, -1.0, -0.1), 1.0);
+        let bounce = CollisionSettings { response: Response::Bounce, restitution: 1.0 };
+        let (mut position, mut velocity) = (Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -2.0, 0.0));
+        let events = move_hull(&hull(&model, position), &mut position, &mut velocity, Vec3::new(0.0, -1.0, 0.0), &[planet], &bounce);
+        assert!((velocity - Vec3::new(0.0, 2.0, 0.0)).norm() < 1e-3, "{}", velocity);
+        assert!((events[0].speed - 2.0).abs() < 1e-3);
+        assert!(position.y > 0.2);
+    }
+}
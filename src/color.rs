@@ -1,35 +1,82 @@
+use nalgebra_glm::Vec3;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+//Color en luz lineal (HDR): los canales no tienen tope, el tone mapping los
+//lleva a la pantalla al final del frame
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8,
+    r: f32,
+    g: f32,
+    b: f32,
 }
 
 impl Color {
-    pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+    //canales de 0 a 255 en sRGB, como en un selector de colores
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        let channel = |value: u8| srgb_to_linear(value as f32 / 255.0);
+        Color { r: channel(r), g: channel(g), b: channel(b) }
     }
 
     pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color { r: 0.0, g: 0.0, b: 0.0 }
     }
 
-    pub fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    //canales lineales (texturas ya decodificadas, luces)
+    pub fn from_vec3(rgb: &Vec3) -> Self {
+        Color { r: rgb.x.max(0.0), g: rgb.y.max(0.0), b: rgb.z.max(0.0) }
     }
+
+    pub fn to_vec3(self) -> Vec3 {
+        Vec3::new(self.r, self.g, self.b)
+    }
+
+    //multiplica cada canal por la luz que le llega (0 = negro, 1 = igual)
+    pub fn modulate(&self, light: &Vec3) -> Self {
+        Color::from_vec3(&self.to_vec3().component_mul(light))
+    }
+
     // Linear interpolation between two colors
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
         Color {
-            r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
-            g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
-            b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
         }
     }
 }
 
+//curva sRGB exacta, de 0 a 1
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+//0xRRGGBB en sRGB -> canales lineales
+pub fn hex_to_linear(hex: u32) -> Vec3 {
+    let channel = |shift: u32| srgb_to_linear(((hex >> shift) & 0xFF) as f32 / 255.0);
+    Vec3::new(channel(16), channel(8), channel(0))
+}
+
+//"#RRGGBB" -> 0xRRGGBB
+pub fn parse_hex(color: &str) -> Option<u32> {
+    color
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+}
+
 use std::ops::Add;
 
 impl Add for Color {
@@ -37,9 +84,9 @@ impl Add for Color {
 
     fn add(self, other: Color) -> Color {
         Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
         }
     }
 }
@@ -50,16 +97,29 @@ impl Mul<f32> for Color {
     type Output = Color;
 
     fn mul(self, scalar: f32) -> Color {
-        Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
-        }
+        Color::from_vec3(&(self.to_vec3() * scalar))
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {:.3}, g: {:.3}, b: {:.3})", self.r, self.g, self.b)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_round_trip() {
+        for value in [0u8, 1, 10, 128, 200, 255] {
+            let linear = srgb_to_linear(value as f32 / 255.0);
+            assert!((linear_to_srgb(linear) * 255.0 - value as f32).abs() < 1e-3);
+        }
+        //el gris medio de la pantalla es ~21% de luz
+        assert!((Color::new(128, 128, 128).to_vec3().x - 0.2158).abs() < 1e-3);
+        //en HDR sumar no satura
+        assert_eq!((Color::new(255, 255, 255) + Color::new(255, 255, 255)).to_vec3().x, 2.0);
+    }
+}
@@ -0,0 +1,159 @@
+use minifb::Key;
+use nalgebra_glm::Vec3;
+use std::collections::HashMap;
+
+use crate::spaceship::ShipInput;
+
+//Teclas de la nave y la camara. Se pueden cambiar en `[controls]` de la escena con
+//`accion = "Tecla"` (nombres de minifb: "W", "Up", "Space", "LeftShift"...).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Forward,
+    Backward,
+    StrafeLeft,
+    StrafeRight,
+    StrafeUp,
+    StrafeDown,
+    PitchUp,
+    PitchDown,
+    YawLeft,
+    YawRight,
+    RollLeft,
+    RollRight,
+    Boost,
+    //prende y apaga el coasting (sin frenado)
+    Coast,
+    //modo de camara siguiente y cuerpo que mira
+    NextCamera,
+    NextTarget,
+}
+
+//nombre en la escena y tecla por defecto
+const ACTIONS: [(&str, Action, Key); 16] = [
+    ("forward", Action::Forward, Key::Up),
+    ("backward", Action::Backward, Key::Down),
+    ("strafe_left", Action::StrafeLeft, Key::Q),
+    ("strafe_right", Action::StrafeRight, Key::E),
+    ("strafe_up", Action::StrafeUp, Key::Space),
+    ("strafe_down", Action::StrafeDown, Key::LeftCtrl),
+    ("pitch_up", Action::PitchUp, Key::S),
+    ("pitch_down", Action::PitchDown, Key::W),
+    ("yaw_left", Action::YawLeft, Key::Left),
+    ("yaw_right", Action::YawRight, Key::Right),
+    ("roll_left", Action::RollLeft, Key::A),
+    ("roll_right", Action::RollRight, Key::D),
+    ("boost", Action::Boost, Key::LeftShift),
+    ("coast", Action::Coast, Key::C),
+    ("next_camera", Action::NextCamera, Key::V),
+    ("next_target", Action::NextTarget, Key::B),
+];
+
+//las usan el reloj, la exposicion y el post-proceso
+const RESERVED: [Key; 18] = [
+    Key::Escape, Key::P, Key::R, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Minus, Key::Equal,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9,
+];
+
+//teclas que se pueden asignar, se buscan por su nombre en minifb
+const KEYS: [Key; 68] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::Up, Key::Down, Key::Left, Key::Right, Key::Space, Key::Tab, Key::Enter, Key::Backspace,
+    Key::LeftShift, Key::RightShift, Key::LeftCtrl, Key::RightCtrl, Key::LeftAlt, Key::RightAlt,
+    Key::Insert, Key::Delete, Key::Home, Key::End, Key::PageUp, Key::PageDown,
+    Key::Comma, Key::Period, Key::Slash, Key::Semicolon, Key::Apostrophe,
+    Key::NumPad0, Key::NumPad2, Key::NumPad4, Key::NumPad5, Key::NumPad6, Key::NumPad8, Key::NumPadPlus,
+];
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEYS.into_iter()
+        .chain(RESERVED)
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Controls {
+    bindings: Vec<(Action, Key)>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls { bindings: ACTIONS.iter().map(|&(_, action, key)| (action, key)).collect() }
+    }
+}
+
+impl Controls {
+    //las acciones que no aparecen quedan con su tecla por defecto
+    pub fn from_names(names: &HashMap<String, String>) -> Result<Self, String> {
+        let mut controls = Controls::default();
+        for (action_name, key_name) in names {
+            let index = ACTIONS
+                .iter()
+                .position(|(name, _, _)| name == action_name)
+                .ok_or_else(|| format!("unknown control: {}", action_name))?;
+            let key = key_from_name(key_name).ok_or_else(|| format!("{}: unknown key {}", action_name, key_name))?;
+            if RESERVED.contains(&key) {
+                return Err(format!("{}: key {} is reserved", action_name, key_name));
+            }
+            controls.bindings[index].1 = key;
+        }
+        for (i, &(action, key)) in controls.bindings.iter().enumerate() {
+            if let Some(&(other, _)) = controls.bindings[i + 1..].iter().find(|(_, other_key)| *other_key == key) {
+                return Err(format!("{:?} is bound to both {:?} and {:?}", key, action, other));
+            }
+        }
+        Ok(controls)
+    }
+
+    pub fn key(&self, action: Action) -> Key {
+        self.bindings.iter().find(|(bound, _)| *bound == action).map(|&(_, key)| key).unwrap()
+    }
+
+    //ejes de la nave segun las teclas apretadas
+    pub fn input(&self, is_down: impl Fn(Key) -> bool) -> ShipInput {
+        let axis = |positive: Action, negative: Action| {
+            (is_down(self.key(positive)) as i32 - is_down(self.key(negative)) as i32) as f32
+        };
+        ShipInput {
+            thrust: Vec3::new(
+                axis(Action::StrafeRight, Action::StrafeLeft),
+                axis(Action::StrafeUp, Action::StrafeDown),
+                axis(Action::Forward, Action::Backward),
+            ),
+            rotation: Vec3::new(
+                axis(Action::PitchUp, Action::PitchDown),
+                axis(Action::YawLeft, Action::YawRight),
+                axis(Action::RollRight, Action::RollLeft),
+            ),
+            boost: is_down(self.key(Action::Boost)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_and_errors() {
+        let names = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(a, k)| (a.to_string(), k.to_string())).collect()
+        };
+        let controls = Controls::from_names(&names(&[("pitch_up", "I"), ("pitch_down", "k")])).unwrap();
+        assert_eq!(controls.key(Action::PitchUp), Key::I);
+        assert_eq!(controls.key(Action::PitchDown), Key::K);
+        assert_eq!(controls.key(Action::Forward), Key::Up);
+
+        let input = controls.input(|key| key == Key::I || key == Key::Up || key == Key::A);
+        assert_eq!(input.thrust, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(input.rotation, Vec3::new(1.0, 0.0, -1.0));
+
+        assert!(Controls::from_names(&names(&[("jump", "J")])).is_err());
+        assert!(Controls::from_names(&names(&[("boost", "Hyperdrive")])).is_err());
+        assert!(Controls::from_names(&names(&[("boost", "P")])).is_err());
+        //A ya es roll_left
+        assert!(Controls::from_names(&names(&[("boost", "A")])).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+//Que caras descartar antes de rasterizar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FaceCulling {
+    //para mallas abiertas o planas (anillos)
+    None,
+    Back,
+}
+
+//Con el viewport invirtiendo y, un triangulo antihorario en NDC (cara
+//frontal de un .obj) queda con area positiva en pantalla
+pub fn is_back_face(a: &Vec3, b: &Vec3, c: &Vec3) -> bool {
+    let area = (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x);
+    area <= 0.0
+}
+
+//Los seis planos del frustum sacados de proyeccion * vista (Gribb-Hartmann),
+//normalizados y apuntando hacia adentro
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| plane / plane.xyz().norm());
+        Frustum { planes }
+    }
+
+    pub fn contains_sphere(&self, center: &Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(center) + plane.w >= -radius)
+    }
+}
+
+//esfera envolvente en mundo de una malla con centro y radio de modelo
+pub fn bounding_sphere(model_matrix: &Mat4, center: &Vec3, radius: f32) -> (Vec3, f32) {
+    let world_center = model_matrix * Vec4::new(center.x, center.y, center.z, 1.0);
+    let scale = (0..3)
+        .map(|i| model_matrix.column(i).xyz().norm())
+        .fold(0.0, f32::max);
+    (world_center.xyz(), radius * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{look_at, perspective};
+
+    #[test]
+    fn sphere_behind_camera_is_culled() {
+        let projection = perspective(1.0, 1.0, 0.1, 100.0);
+        let view = look_at(&Vec3::new(0.0, 0.0, 5.0), &Vec3::zeros(), &Vec3::new(0.0, 1.0, 0.0));
+        let frustum = Frustum::from_matrix(&(projection * view));
+
+        assert!(frustum.contains_sphere(&Vec3::zeros(), 1.0));
+        assert!(!frustum.contains_sphere(&Vec3::new(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.contains_sphere(&Vec3::new(50.0, 0.0, 0.0), 1.0));
+        //toca el borde: se dibuja
+        assert!(frustum.contains_sphere(&Vec3::new(0.0, 0.0, 6.0), 1.5));
+    }
+}
@@ -1,24 +1,32 @@
-use nalgebra_glm::{Vec2, Vec3};
-use crate::color::Color;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+
+use crate::antialiasing::SamplePattern;
 
 pub struct Fragment {
     pub position: Vec2,
-    pub color: Color,
     pub depth: f32,
-    pub normal: Vec3,
-    pub intensity: f32,
+    //posicion en espacio de modelo
     pub vertex_position: Vec3,
+    //posicion y normal (normalizada) en mundo
+    pub world_position: Vec3,
+    pub normal: Vec3,
+    //tangente en mundo, w = sentido de la bitangente
+    pub tangent: Vec4,
+    pub tex_coords: Vec2,
+    //luz de los vertices interpolada (sombreado Gouraud)
+    pub vertex_light: Vec3,
+    //cuanto cambia uv por pixel en este triangulo; elige el mipmap
+    pub uv_footprint: f32,
 }
 
-impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32, normal: Vec3, intensity: f32, vertex_position: Vec3,) -> Self {
-        Fragment {
-            position: Vec2::new(x, y),
-            color,
-            depth,
-            normal,
-            intensity,
-            vertex_position
-        }
-    }
-}
\ No newline at end of file
+//Destino de la rasterizacion: decide la prueba de profundidad y que se hace
+//con cada fragmento visible
+pub trait FragmentSink {
+    //muestras por pixel del destino
+    fn pattern(&self) -> SamplePattern;
+    //true si la muestra esta mas cerca que lo ya dibujado; si pasa, guarda
+    //su profundidad
+    fn depth_test(&mut self, x: usize, y: usize, sample: usize, depth: f32) -> bool;
+    //colorea las muestras de `coverage` (bit i = muestra i) del pixel
+    fn shade(&mut self, fragment: &Fragment, coverage: u32);
+}
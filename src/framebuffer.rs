@@ -1,59 +1,135 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use nalgebra_glm::Vec3;
+use std::path::Path;
+
+use crate::antialiasing::Antialiasing;
+use crate::color::hex_to_linear;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    //imagen final en sRGB, un color por pixel (la llena `hdr::present`)
     pub buffer: Vec<u32>,
+    //color HDR en luz lineal por pixel (lo llena `resolve`)
+    pub color: Vec<Vec3>,
+    //color y profundidad de cada muestra: pixel * muestras + muestra
+    pub samples: Vec<Vec3>,
     pub zbuffer: Vec<f32>,
-    background_color: u32,
-    current_color: u32,
-    background_buffer: Vec<u32>
+    antialiasing: Antialiasing,
+    //fondo en luz lineal
+    background_buffer: Vec<Vec3>,
+    //hilos para rasterizar; con 1 se usa el camino de un solo hilo
+    pub threads: usize,
 }
 
 impl Framebuffer {
     pub fn new(width: usize, height: usize) -> Self {
-        let background_color = 0x151515;
         Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            color: vec![Vec3::zeros(); width * height],
+            samples: vec![Vec3::zeros(); width * height],
             zbuffer: vec![f32::INFINITY; width * height],
-            background_color: 0x151515,
-            current_color: 0xFFFFFF,
-            background_buffer: vec![background_color; width * height]
+            antialiasing: Antialiasing::None,
+            background_buffer: vec![hex_to_linear(0x151515); width * height],
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
         }
     }
 
+    //cambia la cantidad de muestras por pixel; borra lo dibujado
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.antialiasing = antialiasing;
+        let samples = self.width * self.height * antialiasing.samples();
+        self.samples = vec![Vec3::zeros(); samples];
+        self.zbuffer = vec![f32::INFINITY; samples];
+        self.clear();
+    }
+
+    pub fn antialiasing(&self) -> Antialiasing {
+        self.antialiasing
+    }
+
     pub fn clear(&mut self) {
-        // Copiar el contenido de background_buffer a buffer
-        self.buffer.copy_from_slice(&self.background_buffer);
+        // Copiar el contenido de background_buffer a cada muestra
+        let samples = self.antialiasing.samples();
+        for (pixel, &color) in self.samples.chunks_exact_mut(samples).zip(&self.background_buffer) {
+            pixel.fill(color);
+        }
+        self.color.copy_from_slice(&self.background_buffer);
     
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
     }
 
-    pub fn point(&mut self, x: usize, y: usize, depth: f32) {
-        if x < self.width && y < self.height {
-            let index = y * self.width + x;
+    //promedia las muestras de cada pixel en `color` (en luz lineal)
+    pub fn resolve(&mut self) {
+        let samples = self.antialiasing.samples();
+        if samples == 1 {
+            self.color.copy_from_slice(&self.samples);
+            return;
+        }
+        for (pixel, colors) in self.color.iter_mut().zip(self.samples.chunks_exact(samples)) {
+            *pixel = colors.iter().sum::<Vec3>() / samples as f32;
+        }
+    }
 
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+    //fondo en luz lineal que `clear` copia a cada pixel (el cielo)
+    pub fn background_mut(&mut self) -> &mut [Vec3] {
+        &mut self.background_buffer
+    }
+
+    //copia la imagen final a `target` agrandando cada pixel (sin suavizar),
+    //para dibujar a baja resolucion y mostrar en la ventana
+    pub fn upscale_to(&self, target: &mut Framebuffer) {
+        for y in 0..target.height {
+            let source_row = y * self.height / target.height * self.width;
+            let row = &mut target.buffer[y * target.width..(y + 1) * target.width];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.buffer[source_row + x * self.width / target.width];
             }
         }
     }
 
-    pub fn set_background_color(&mut self, color: u32) {
-        self.background_color = color;
+    //exportar frame, formato segun extension (.png o .ppm)
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => self.save_png(path),
+            Some("ppm") => self.save_ppm(path),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported image format: {}", path.display()),
+            )),
+        }
     }
 
-    pub fn set_current_color(&mut self, color: u32) {
-        self.current_color = color;
-    }
-    //espacio
-    pub fn set_background_star(&mut self, x: usize, y: usize, color: u32) {
-        if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            self.background_buffer[index] = color;
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(self.width * self.height * 3);
+        for &pixel in &self.buffer {
+            rgb.push((pixel >> 16) as u8);
+            rgb.push((pixel >> 8) as u8);
+            rgb.push(pixel as u8);
         }
+        rgb
     }
-}
\ No newline at end of file
+
+    fn save_ppm(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(file, "P6\n{} {}\n255\n", self.width, self.height)?;
+        file.write_all(&self.to_rgb8())?;
+        file.flush()
+    }
+
+    fn save_png(&self, path: &Path) -> io::Result<()> {
+        image::save_buffer(
+            path,
+            &self.to_rgb8(),
+            self.width as u32,
+            self.height as u32,
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,257 @@
+//Pruebas de imagen de referencia (golden images)
+//
+//Cada prueba renderiza un objeto fijo a un `time` fijo y lo compara con
+//tests/golden/<nombre>.png. Con BLESS_GOLDEN=1 se reescriben las referencias.
+//Si falla, la imagen obtenida y un diff quedan en target/golden-diff/.
+use nalgebra_glm::Vec3;
+use std::path::PathBuf;
+
+use crate::antialiasing::Antialiasing;
+use crate::camera::{Camera, DEFAULT_FOV};
+use crate::culling::FaceCulling;
+use crate::framebuffer::Framebuffer;
+use crate::hdr::{self, HdrSettings};
+use crate::lighting::{Light, Lighting, Shading};
+use crate::mesh::Mesh;
+use crate::obj::Obj;
+use crate::postprocess::{Effect, Pass, PostProcess};
+use crate::scene::{Scene, SceneDescription, DEFAULT_SCENE};
+use crate::shaders::{ShaderParams, ShaderRegistry};
+use crate::texture::TextureSet;
+use crate::{
+    create_model_matrix, create_noise, create_perspective_matrix, create_view_matrix,
+    create_viewport_matrix, render, render_frame, Uniforms,
+};
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 120;
+const TIME: f32 = 0.7;
+//diferencia maxima por canal antes de contar un pixel como distinto
+const CHANNEL_TOLERANCE: u8 = 2;
+const MAX_MISMATCHED_PIXELS: usize = 0;
+
+fn manifest_path(relative: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(relative)
+}
+
+fn load_model(name: &str) -> Mesh {
+    let path = manifest_path(&format!("assets/models/{}", name));
+    Obj::load(path.to_str().unwrap())
+        .expect("Failed to load obj")
+        .get_mesh()
+}
+
+fn bounds_center(mesh: &Mesh) -> Vec3 {
+    let mut min = Vec3::repeat(f32::INFINITY);
+    let mut max = Vec3::repeat(f32::NEG_INFINITY);
+    for vertex in &mesh.vertices {
+        min = min.inf(&vertex.position);
+        max = max.sup(&vertex.position);
+    }
+    (min + max) * 0.5
+}
+
+//objeto solo, centrado y visto desde +z
+fn render_object(
+    mesh: &Mesh,
+    scale: f32,
+    rotation: Vec3,
+    shader_type: &str,
+    distance: f32,
+    antialiasing: Antialiasing,
+    shading: Shading,
+) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.set_antialiasing(antialiasing);
+
+    let model_matrix = create_model_matrix(Vec3::zeros(), scale, rotation);
+    let center = (model_matrix * bounds_center(mesh).push(1.0)).xyz();
+    let eye = center + Vec3::new(0.0, 0.0, distance);
+
+    let uniforms = Uniforms {
+        model_matrix,
+        view_matrix: create_view_matrix(eye, center, Vec3::new(0.0, 1.0, 0.0)),
+        projection_matrix: create_perspective_matrix(WIDTH as f32, HEIGHT as f32, DEFAULT_FOV),
+        viewport_matrix: create_viewport_matrix(WIDTH as f32, HEIGHT as f32),
+        time: TIME,
+        noise: create_noise(),
+        params: ShaderParams::new(),
+        //luz blanca desde la camara, sin ambiente
+        lighting: Lighting::new(vec![Light::Directional {
+            direction: Vec3::new(0.0, 0.0, -1.0),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+        }]),
+        camera_position: eye,
+        textures: TextureSet::default(),
+        shading,
+        two_sided: false,
+    };
+
+    let shader = ShaderRegistry::default().program(shader_type).unwrap();
+    render(&mut framebuffer, &uniforms, mesh, &shader, FaceCulling::Back);
+    framebuffer.resolve();
+    hdr::present(&mut framebuffer, &HdrSettings::default());
+    framebuffer
+}
+
+fn channels(pixel: u32) -> [u8; 3] {
+    [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]
+}
+
+fn check_golden(name: &str, framebuffer: &Framebuffer) {
+    let reference_path = manifest_path(&format!("tests/golden/{}.png", name));
+
+    if std::env::var_os("BLESS_GOLDEN").is_some() {
+        std::fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        framebuffer.save(&reference_path).unwrap();
+        return;
+    }
+
+    let reference = image::open(&reference_path)
+        .unwrap_or_else(|err| panic!("missing golden image {}: {} (run with BLESS_GOLDEN=1)", reference_path.display(), err))
+        .to_rgb8();
+    assert_eq!(
+        (reference.width() as usize, reference.height() as usize),
+        (framebuffer.width, framebuffer.height),
+        "golden image {} has a different size",
+        name
+    );
+
+    let mut diff = Framebuffer::new(framebuffer.width, framebuffer.height);
+    let mut mismatched = 0;
+    for (i, &pixel) in framebuffer.buffer.iter().enumerate() {
+        let expected = reference.as_raw()[i * 3..i * 3 + 3].to_vec();
+        let actual = channels(pixel);
+        let differs = actual
+            .iter()
+            .zip(&expected)
+            .any(|(&a, &e)| a.abs_diff(e) > CHANNEL_TOLERANCE);
+
+        diff.buffer[i] = if differs {
+            mismatched += 1;
+            0xFF0000
+        } else {
+            //referencia oscurecida para dar contexto
+            let [r, g, b] = [expected[0] / 4, expected[1] / 4, expected[2] / 4];
+            ((r as u32) << 16) | ((g as u32) << 8) | b as u32
+        };
+    }
+
+    if mismatched > MAX_MISMATCHED_PIXELS {
+        let out_dir = manifest_path("target/golden-diff");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        framebuffer.save(&out_dir.join(format!("{}.actual.png", name))).unwrap();
+        diff.save(&out_dir.join(format!("{}.diff.png", name))).unwrap();
+        panic!(
+            "{}: {} pixels differ from {} (diff written to {})",
+            name,
+            mismatched,
+            reference_path.display(),
+            out_dir.display()
+        );
+    }
+}
+
+fn check_planet(name: &str, shader_type: &str) {
+    let sphere = load_model("sphere.obj");
+    let framebuffer = render_object(&sphere, 1.0, Vec3::zeros(), shader_type, 3.0, Antialiasing::None, Shading::Pixel);
+    check_golden(name, &framebuffer);
+}
+
+#[test]
+fn sun() {
+    let sphere = load_model("sphere.obj");
+    let framebuffer = render_object(&sphere, 1.5, Vec3::zeros(), "lava_shader", 4.5, Antialiasing::None, Shading::Pixel);
+    check_golden("sun", &framebuffer);
+}
+
+#[test]
+fn continents_planet() {
+    check_planet("continents", "continents_shader");
+}
+
+#[test]
+fn another_planet() {
+    check_planet("another", "another_shader");
+}
+
+#[test]
+fn gradient_planet() {
+    check_planet("gradient", "gradient_shader");
+}
+
+#[test]
+fn lines_planet() {
+    check_planet("lines", "lines_shader");
+}
+
+//preset n64: luz por vertice y color de 15 bits con dithering
+#[test]
+fn n64_planet() {
+    let sphere = load_model("sphere.obj");
+    let mut framebuffer =
+        render_object(&sphere, 1.0, Vec3::zeros(), "continents_shader", 3.0, Antialiasing::None, Shading::Gouraud);
+    let post = PostProcess {
+        passes: vec![Pass { effect: Effect::Dither { bits: 5 }, enabled: true }],
+    };
+    post.apply(&mut framebuffer, TIME);
+    check_golden("continents_n64", &framebuffer);
+}
+
+#[test]
+fn spaceship() {
+    let ship = load_model("nave.obj");
+    let framebuffer = render_object(&ship, 1.0, Vec3::new(0.4, 0.6, 0.0), "spaceship_shader", 2.0, Antialiasing::None, Shading::Pixel);
+    check_golden("spaceship", &framebuffer);
+}
+
+//el rasterizador por tiles debe dar exactamente lo mismo que un solo hilo
+#[test]
+fn tiled_matches_single_threaded() {
+    let description = SceneDescription::load(&manifest_path(DEFAULT_SCENE).to_string_lossy()).unwrap();
+    let mut scene = Scene::build(&description, &ShaderRegistry::default()).unwrap();
+    scene.camera = Camera::new(Vec3::new(0.0, 6.0, -18.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+    scene.update(3.0);
+
+    let render_with = |threads: usize, antialiasing: Antialiasing| {
+        let mut framebuffer = Framebuffer::new(WIDTH * 2, HEIGHT * 2);
+        framebuffer.threads = threads;
+        framebuffer.set_antialiasing(antialiasing);
+        render_frame(&mut framebuffer, &scene, TIME);
+        framebuffer
+    };
+    for antialiasing in [Antialiasing::None, Antialiasing::Msaa(4), Antialiasing::Ssaa(2)] {
+        let single = render_with(1, antialiasing);
+        let tiled = render_with(4, antialiasing);
+
+        assert!(single.zbuffer.iter().any(|depth| depth.is_finite()), "nothing was drawn");
+        assert!(single.buffer == tiled.buffer, "tiled color differs from single-threaded ({:?})", antialiasing);
+        assert!(single.zbuffer == tiled.zbuffer, "tiled depth differs from single-threaded ({:?})", antialiasing);
+    }
+}
+
+//con MSAA los bordes mezclan el objeto con el fondo y el interior sale igual
+#[test]
+fn msaa_blends_edges_only() {
+    let ship = load_model("nave.obj");
+    let render_with = |antialiasing: Antialiasing| {
+        render_object(&ship, 1.0, Vec3::new(0.4, 0.6, 0.0), "spaceship_shader", 2.0, antialiasing, Shading::Pixel)
+    };
+    let aliased = render_with(Antialiasing::None);
+    let smooth = render_with(Antialiasing::Msaa(4));
+    check_golden("spaceship_msaa4", &smooth);
+
+    let background = aliased.buffer[0];
+    let mut blended = 0;
+    for (&hard, &soft) in aliased.buffer.iter().zip(&smooth.buffer) {
+        if hard != soft && soft != background {
+            blended += 1;
+        }
+    }
+    let differing = aliased.buffer.iter().zip(&smooth.buffer).filter(|(a, b)| a != b).count();
+    assert!(blended > 0, "no edge was smoothed");
+    //solo cambian los bordes, no todo el objeto
+    let drawn = aliased.buffer.iter().filter(|&&pixel| pixel != background).count();
+    assert!(differing < drawn / 2, "{} of {} pixels changed", differing, drawn);
+}
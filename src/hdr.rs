@@ -0,0 +1,234 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+use crate::color::linear_to_srgb;
+use crate::framebuffer::Framebuffer;
+
+//Del color HDR (luz lineal) a la imagen de 8 bits: bloom, exposicion, tone
+//mapping y codificacion sRGB.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMapping {
+    //recorta en 1 (como antes del HDR)
+    Clamp,
+    //c / (1 + c), suave pero apagado
+    Reinhard,
+    //curva filmica de ACES (aproximacion de Narkowicz)
+    #[default]
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        match self {
+            ToneMapping::Clamp => color,
+            ToneMapping::Reinhard => color.component_div(&(color + Vec3::repeat(1.0))),
+            ToneMapping::Aces => color.map(|c| {
+                let (a, b, c2, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                (c * (a * c + b)) / (c * (c2 * c + d) + e)
+            }),
+        }
+        .map(|c| c.clamp(0.0, 1.0))
+    }
+}
+
+//Lo que pasa de `threshold` se difumina y se suma encima
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bloom {
+    pub threshold: f32,
+    pub intensity: f32,
+    //radio del desenfoque en pixeles de pantalla
+    pub radius: usize,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom { threshold: 1.0, intensity: 0.8, radius: 16 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrSettings {
+    pub exposure: f32,
+    pub tone_mapping: ToneMapping,
+    pub bloom: Option<Bloom>,
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        HdrSettings {
+            exposure: 1.0,
+            tone_mapping: ToneMapping::default(),
+            bloom: Some(Bloom::default()),
+        }
+    }
+}
+
+//Pasa `framebuffer.color` (ya resuelto) a `framebuffer.buffer`
+pub fn present(framebuffer: &mut Framebuffer, settings: &HdrSettings) {
+    let glow = settings
+        .bloom
+        .filter(|bloom| bloom.intensity > 0.0)
+        .map(|bloom| (bloom, bloom_buffer(framebuffer, &bloom)));
+
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let mut color = framebuffer.color[i];
+            if let Some((bloom, (small, small_width, small_height))) = &glow {
+                //el bloom esta a menor resolucion: se lee con filtro bilineal
+                let scale = 1.0 / BLOOM_DOWNSAMPLE as f32;
+                let (u, v) = ((x as f32 + 0.5) * scale, (y as f32 + 0.5) * scale);
+                color += sample_bilinear(small, *small_width, *small_height, u, v) * bloom.intensity;
+            }
+            let mapped = settings.tone_mapping.apply(color * settings.exposure);
+            framebuffer.buffer[i] = encode_srgb(&mapped);
+        }
+    }
+}
+
+//el bloom se calcula a 1/4 de resolucion: el desenfoque cuesta 16 veces menos
+const BLOOM_DOWNSAMPLE: usize = 4;
+
+//partes brillantes a baja resolucion, desenfocadas con un gaussiano separable
+fn bloom_buffer(framebuffer: &Framebuffer, bloom: &Bloom) -> (Vec<Vec3>, usize, usize) {
+    let width = framebuffer.width.div_ceil(BLOOM_DOWNSAMPLE);
+    let height = framebuffer.height.div_ceil(BLOOM_DOWNSAMPLE);
+    let mut bright = vec![Vec3::zeros(); width * height];
+    for y in 0..framebuffer.height {
+        let row = (y / BLOOM_DOWNSAMPLE) * width;
+        for x in 0..framebuffer.width {
+            bright[row + x / BLOOM_DOWNSAMPLE] += framebuffer.color[y * framebuffer.width + x];
+        }
+    }
+    //promedio de cada bloque, solo lo que pasa del umbral
+    let block = (BLOOM_DOWNSAMPLE * BLOOM_DOWNSAMPLE) as f32;
+    for texel in bright.iter_mut() {
+        *texel = bright_pass(&(*texel / block), bloom.threshold);
+    }
+
+    let kernel = gaussian_kernel((bloom.radius / BLOOM_DOWNSAMPLE).max(1));
+    let horizontal = blur(&bright, width, height, &kernel, (1, 0));
+    let blurred = blur(&horizontal, width, height, &kernel, (0, 1));
+    (blurred, width, height)
+}
+
+//conserva el tono: escala el color por cuanto su brillo pasa del umbral
+fn bright_pass(color: &Vec3, threshold: f32) -> Vec3 {
+    let brightness = color.max();
+    if brightness <= threshold || brightness <= 0.0 {
+        return Vec3::zeros();
+    }
+    color * ((brightness - threshold) / brightness)
+}
+
+//pesos de -radius a radius, suman 1
+fn gaussian_kernel(radius: usize) -> Vec<f32> {
+    let sigma = radius as f32 / 2.0;
+    let weights: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let x = i as f32 - radius as f32;
+            (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+fn blur(source: &[Vec3], width: usize, height: usize, kernel: &[f32], (dx, dy): (usize, usize)) -> Vec<Vec3> {
+    let radius = (kernel.len() / 2) as isize;
+    let mut output = vec![Vec3::zeros(); source.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = Vec3::zeros();
+            for (k, weight) in kernel.iter().enumerate() {
+                let offset = k as isize - radius;
+                //el borde se repite
+                let sx = (x as isize + offset * dx as isize).clamp(0, width as isize - 1) as usize;
+                let sy = (y as isize + offset * dy as isize).clamp(0, height as isize - 1) as usize;
+                sum += source[sy * width + sx] * *weight;
+            }
+            output[y * width + x] = sum;
+        }
+    }
+    output
+}
+
+fn sample_bilinear(buffer: &[Vec3], width: usize, height: usize, x: f32, y: f32) -> Vec3 {
+    let (x, y) = ((x - 0.5).max(0.0), (y - 0.5).max(0.0));
+    let (x0, y0) = (x as usize, y as usize);
+    let (x0, y0) = (x0.min(width - 1), y0.min(height - 1));
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    let top = buffer[y0 * width + x0].lerp(&buffer[y0 * width + x1], tx.clamp(0.0, 1.0));
+    let bottom = buffer[y1 * width + x0].lerp(&buffer[y1 * width + x1], tx.clamp(0.0, 1.0));
+    top.lerp(&bottom, ty.clamp(0.0, 1.0))
+}
+
+//tabla de 0..1 lineal a sRGB de 8 bits; evita un powf por canal y pixel
+const SRGB_TABLE_SIZE: usize = 4096;
+
+fn srgb_table() -> &'static [u8; SRGB_TABLE_SIZE] {
+    static TABLE: OnceLock<[u8; SRGB_TABLE_SIZE]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0; SRGB_TABLE_SIZE];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let linear = i as f32 / (SRGB_TABLE_SIZE - 1) as f32;
+            *entry = (linear_to_srgb(linear) * 255.0).round() as u8;
+        }
+        table
+    })
+}
+
+//color lineal de 0 a 1 -> 0xRRGGBB
+pub fn encode_srgb(color: &Vec3) -> u32 {
+    let table = srgb_table();
+    //+0.5 y truncar redondea (los valores ya son positivos) sin llamar a `round`
+    let channel = |value: f32| table[(value.clamp(0.0, 1.0) * (SRGB_TABLE_SIZE - 1) as f32 + 0.5) as usize] as u32;
+    (channel(color.x) << 16) | (channel(color.y) << 8) | channel(color.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_mapping_keeps_range_and_order() {
+        for tone_mapping in [ToneMapping::Clamp, ToneMapping::Reinhard, ToneMapping::Aces] {
+            let mut previous = -1.0;
+            for value in [0.0, 0.1, 0.5, 1.0, 4.0, 100.0] {
+                let mapped = tone_mapping.apply(Vec3::repeat(value)).x;
+                assert!((0.0..=1.0).contains(&mapped));
+                assert!(mapped >= previous, "{:?} is not monotonic", tone_mapping);
+                previous = mapped;
+            }
+        }
+        assert_eq!(ToneMapping::Reinhard.apply(Vec3::repeat(1.0)).x, 0.5);
+    }
+
+    #[test]
+    fn srgb_encoding_matches_the_curve() {
+        assert_eq!(encode_srgb(&Vec3::zeros()), 0x000000);
+        assert_eq!(encode_srgb(&Vec3::repeat(1.0)), 0xFFFFFF);
+        assert_eq!(encode_srgb(&Vec3::new(0.2158, 0.0, 5.0)) >> 16, 128);
+    }
+
+    #[test]
+    fn bloom_spreads_only_bright_pixels() {
+        let mut framebuffer = Framebuffer::new(32, 32);
+        framebuffer.background_mut().fill(Vec3::zeros());
+        framebuffer.clear();
+        framebuffer.color.fill(Vec3::repeat(0.5));
+        framebuffer.color[16 * 32 + 16] = Vec3::repeat(50.0);
+
+        let settings = HdrSettings { exposure: 1.0, tone_mapping: ToneMapping::Clamp, bloom: Some(Bloom::default()) };
+        present(&mut framebuffer, &settings);
+        let dim = encode_srgb(&Vec3::repeat(0.5));
+        //el vecino del punto brillante recibe luz, la esquina no
+        assert!(framebuffer.buffer[16 * 32 + 19] > dim);
+        assert_eq!(framebuffer.buffer[0], dim);
+    }
+}
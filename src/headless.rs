@@ -0,0 +1,271 @@
+use nalgebra_glm::Vec3;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::antialiasing::Antialiasing;
+use crate::camera::Camera;
+use crate::camera_rig::CameraMode;
+use crate::clock::SimulationClock;
+use crate::framebuffer::Framebuffer;
+use crate::spaceship::ShipInput;
+use crate::{render_frame, Scene};
+
+//tiempo real fijo por frame para que la salida sea repetible
+const FRAME_SECONDS: f32 = 1.0 / 60.0;
+
+pub const USAGE: &str = "\
+uso: SpaceTravel --headless [opciones]
+  --size WxH          resolucion (800x600)
+  --frames N          cantidad de frames a escribir (1; en modo track, hasta el final del recorrido)
+  --start N           primer frame a escribir (0)
+  --out DIR           carpeta de salida (frames)
+  --format png|ppm    formato de imagen (png)
+  --eye x,y,z         posicion de la camara (por defecto la mueve su modo)
+  --center x,y,z      punto al que mira la camara
+  --up x,y,z          vector arriba de la camara (0,1,0)
+  --time-scale X      segundos simulados por segundo real (1, negativo = atras)
+tambien con o sin ventana:
+  --scene ARCHIVO     escena a cargar (assets/scenes/solar_system.toml)
+  --physics MODO      kepler o nbody (lo que diga la escena)
+  --threads N         hilos del rasterizador (todos los nucleos; 1 = sin tiles)
+  --aa MODO           antialiasing: none, ssaa2-4 o msaa2/4/8 (none)
+  --preset NOMBRE     modern o n64 (lo que diga la escena)
+  --camera MODO       chase, orbit, free, cockpit, tracking o track (lo que diga la escena)
+  --track ARCHIVO     recorrido de camara a reproducir (activa el modo track)";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+//opciones para renderizar sin ventana
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub width: usize,
+    pub height: usize,
+    //sin valor: 1, o todo el recorrido si la camara reproduce uno
+    pub frames: Option<u32>,
+    pub start_frame: u32,
+    pub output_dir: PathBuf,
+    pub format: ImageFormat,
+    pub eye: Option<Vec3>,
+    pub center: Option<Vec3>,
+    pub up: Vec3,
+    pub time_scale: f32,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        HeadlessOptions {
+            width: 800,
+            height: 600,
+            frames: None,
+            start_frame: 0,
+            output_dir: PathBuf::from("frames"),
+            format: ImageFormat::Png,
+            eye: None,
+            center: None,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            time_scale: 1.0,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    /// Devuelve `None` si no se paso `--headless`.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        if !args.iter().any(|arg| arg == "--headless") {
+            return Ok(None);
+        }
+
+        let mut options = HeadlessOptions::default();
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            if arg == "--headless" {
+                continue;
+            }
+            let value = iter.next().ok_or_else(|| format!("{} requires a value", arg))?;
+            match arg.as_str() {
+                "--size" => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .ok_or_else(|| format!("invalid size: {}", value))?;
+                    options.width = parse_number(width)?;
+                    options.height = parse_number(height)?;
+                }
+                "--frames" => options.frames = Some(parse_number(value)?),
+                "--start" => options.start_frame = parse_number(value)?,
+                "--out" => options.output_dir = PathBuf::from(value),
+                "--format" => {
+                    options.format = match value.as_str() {
+                        "png" => ImageFormat::Png,
+                        "ppm" => ImageFormat::Ppm,
+                        _ => return Err(format!("unknown image format: {}", value)),
+                    }
+                }
+                "--eye" => options.eye = Some(parse_vec3(value)?),
+                "--center" => options.center = Some(parse_vec3(value)?),
+                "--up" => options.up = parse_vec3(value)?,
+                "--time-scale" => options.time_scale = parse_number(value)?,
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+
+        if options.width == 0 || options.height == 0 {
+            return Err("size must be greater than zero".to_string());
+        }
+
+        Ok(Some(options))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("invalid number: {}", value))
+}
+
+fn parse_vec3(value: &str) -> Result<Vec3, String> {
+    let parts: Vec<f32> = value
+        .split(',')
+        .map(parse_number)
+        .collect::<Result<_, _>>()?;
+    match parts[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("expected x,y,z: {}", value)),
+    }
+}
+
+//Simula igual que la ventana pero escribe cada frame a disco
+pub fn run(
+    options: &HeadlessOptions,
+    scene: &mut Scene,
+    threads: Option<usize>,
+    antialiasing: Antialiasing,
+) -> io::Result<()> {
+    fs::create_dir_all(&options.output_dir)?;
+
+    //el preset puede dibujar a menos resolucion; se agranda al guardar
+    let (width, height) = scene.preset.resolution().unwrap_or((options.width, options.height));
+    let mut framebuffer = Framebuffer::new(width, height);
+    let mut output = ((width, height) != (options.width, options.height))
+        .then(|| Framebuffer::new(options.width, options.height));
+    if let Some(threads) = threads {
+        framebuffer.threads = threads;
+    }
+    framebuffer.set_antialiasing(antialiasing);
+
+    let mut clock = SimulationClock::new();
+    clock.set_time_scale(options.time_scale);
+    //sin `--frames`, en modo track se llega hasta el ultimo frame del recorrido
+    let end_frame = match (options.frames, scene.camera_rig.track()) {
+        (Some(frames), _) => options.start_frame + frames,
+        (None, Some(track)) if scene.camera_rig.mode() == CameraMode::Track => {
+            ((track.duration() / FRAME_SECONDS).round() as u32 + 1).max(options.start_frame + 1)
+        }
+        (None, _) => options.start_frame + 1,
+    };
+
+    for frame in 0..end_frame {
+        scene.update(clock.advance(FRAME_SECONDS));
+
+        match (options.eye, options.center) {
+            (None, None) => scene.update_camera(&ShipInput::default(), FRAME_SECONDS),
+            (eye, center) => {
+                scene.camera = Camera::new(
+                    eye.unwrap_or(scene.camera.eye),
+                    center.unwrap_or(scene.spaceship.position),
+                    options.up,
+                );
+            }
+        }
+
+        for event in scene.take_collisions() {
+            println!("frame {}: collision with {} at {:.2}", frame, scene.graph.node(event.node).name, event.speed);
+        }
+
+        if frame < options.start_frame {
+            continue;
+        }
+
+        render_frame(&mut framebuffer, scene, clock.elapsed());
+
+        let path = options
+            .output_dir
+            .join(format!("frame_{:05}.{}", frame, options.format.extension()));
+        match output.as_mut() {
+            Some(output) => {
+                framebuffer.upscale_to(output);
+                output.save(&path)?;
+            }
+            None => framebuffer.save(&path)?,
+        }
+
+        if let Some(gravity) = &scene.gravity {
+            let (diagnostics, drift) = gravity.diagnostics();
+            println!(
+                "frame {}: energy {:.6} (drift {:+.3e}) momentum {:.3e} angular momentum {:.6}",
+                frame,
+                diagnostics.total_energy(),
+                drift,
+                diagnostics.momentum.norm(),
+                diagnostics.angular_momentum.norm()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<HeadlessOptions>, String> {
+        let args: Vec<String> = std::iter::once("SpaceTravel").chain(args.split_whitespace()).map(String::from).collect();
+        HeadlessOptions::from_args(&args)
+    }
+
+    #[test]
+    fn parses_valid_flags() {
+        assert!(parse("--size 320x240").unwrap().is_none());
+        let options = parse("--headless --size 320x240 --frames 10 --start 4 --out renders --format ppm --eye 1,2,3 --up 0,0,1 --time-scale -2")
+            .unwrap()
+            .unwrap();
+        assert_eq!((options.width, options.height), (320, 240));
+        assert_eq!((options.frames, options.start_frame), (Some(10), 4));
+        assert_eq!(options.output_dir, PathBuf::from("renders"));
+        assert_eq!(options.format, ImageFormat::Ppm);
+        assert_eq!((options.eye, options.center), (Some(Vec3::new(1.0, 2.0, 3.0)), None));
+        assert_eq!(options.up, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(options.time_scale, -2.0);
+    }
+
+    #[test]
+    fn rejects_bad_flags() {
+        assert_eq!(parse("--headless --frames").unwrap_err(), "--frames requires a value");
+        assert_eq!(parse("--headless --fps 30").unwrap_err(), "unknown option: --fps");
+        assert_eq!(parse("--headless --frames -1").unwrap_err(), "invalid number: -1");
+        assert_eq!(parse("--headless --frames ten").unwrap_err(), "invalid number: ten");
+        assert_eq!(parse("--headless --size 0x240").unwrap_err(), "size must be greater than zero");
+        assert_eq!(parse("--headless --format gif").unwrap_err(), "unknown image format: gif");
+    }
+
+    #[test]
+    fn rejects_malformed_vectors() {
+        assert_eq!(parse_vec3(" 1, 2 ,3").unwrap(), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(parse_vec3("1,2").unwrap_err(), "expected x,y,z: 1,2");
+        assert_eq!(parse_vec3("1,2,3,4").unwrap_err(), "expected x,y,z: 1,2,3,4");
+        assert_eq!(parse_vec3("1,y,3").unwrap_err(), "invalid number: y");
+        assert_eq!(parse_vec3("").unwrap_err(), "invalid number: ");
+    }
+}
@@ -0,0 +1,168 @@
+use nalgebra_glm::Vec3;
+
+//Luces en espacio de mundo. `color` va de 0 a 1 por canal y se multiplica
+//por `intensity`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light {
+    //luz que llega igual a todo, tambien al lado de noche
+    Ambient { color: Vec3, intensity: f32 },
+    //`direction` es hacia donde viaja la luz
+    Directional { direction: Vec3, color: Vec3, intensity: f32 },
+    //se atenua con 1 / (1 + attenuation * d^2)
+    Point { position: Vec3, color: Vec3, intensity: f32, attenuation: f32 },
+}
+
+//Brillo especular de la superficie (Blinn-Phong)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub specular: f32,
+    pub shininess: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material { specular: 0.0, shininess: 32.0 }
+    }
+}
+
+//Donde se calcula la luz de los shaders que usan `lit`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Shading {
+    //en cada fragmento, con normal maps y especular
+    #[default]
+    Pixel,
+    //solo difusa, en los vertices, interpolada por el triangulo (N64)
+    Gouraud,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Lighting {
+    pub lights: Vec<Light>,
+}
+
+impl Lighting {
+    pub fn new(lights: Vec<Light>) -> Self {
+        Lighting { lights }
+    }
+
+    //luz difusa (Lambert) y especular (Blinn-Phong) que llega a un punto.
+    //`two_sided` es para superficies que se dibujan sin culling (anillos)
+    pub fn light_at(
+        &self,
+        position: &Vec3,
+        normal: &Vec3,
+        eye: &Vec3,
+        material: &Material,
+        two_sided: bool,
+    ) -> (Vec3, Vec3) {
+        let mut diffuse = Vec3::zeros();
+        let mut specular = Vec3::zeros();
+        let to_eye = (eye - position).try_normalize(f32::EPSILON).unwrap_or(*normal);
+        let normal = normal.try_normalize(f32::EPSILON).unwrap_or(to_eye);
+        //en las de dos caras se ve la de atras: la normal mira siempre a la camara.
+        //En mallas cerradas no se invierte, aunque cerca del borde apunte al otro lado
+        let normal = if two_sided && normal.dot(&to_eye) < 0.0 { -normal } else { normal };
+
+        for light in &self.lights {
+            let (to_light, radiance) = match *light {
+                Light::Ambient { color, intensity } => {
+                    diffuse += color * intensity;
+                    continue;
+                }
+                Light::Directional { direction, color, intensity } => (-direction.normalize(), color * intensity),
+                Light::Point { position: light_position, color, intensity, attenuation } => {
+                    let offset = light_position - position;
+                    let distance2 = offset.norm_squared();
+                    let Some(to_light) = offset.try_normalize(f32::EPSILON) else { continue };
+                    (to_light, color * (intensity / (1.0 + attenuation * distance2)))
+                }
+            };
+
+            let lambert = normal.dot(&to_light);
+            if lambert <= 0.0 {
+                continue;
+            }
+            diffuse += radiance * lambert;
+
+            if material.specular > 0.0 {
+                let half = (to_light + to_eye).normalize();
+                let highlight = normal.dot(&half).max(0.0).powf(material.shininess);
+                specular += radiance * (highlight * material.specular);
+            }
+        }
+
+        (diffuse, specular)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sun() -> Lighting {
+        Lighting::new(vec![Light::Point {
+            position: Vec3::zeros(),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+            attenuation: 0.0,
+        }])
+    }
+
+    #[test]
+    fn point_light_lights_the_day_side_only() {
+        let lighting = sun();
+        let planet = Vec3::new(5.0, 0.0, 0.0);
+        let eye = Vec3::new(5.0, 0.0, 10.0);
+        let material = Material::default();
+
+        let (day, _) = lighting.light_at(&(planet - Vec3::x()), &-Vec3::x(), &(planet - Vec3::x() * 3.0), &material, false);
+        let (night, _) = lighting.light_at(&(planet + Vec3::x()), &Vec3::x(), &(planet + Vec3::x() * 3.0), &material, false);
+        let (terminator, _) = lighting.light_at(&(planet + Vec3::z()), &Vec3::z(), &eye, &material, false);
+
+        assert!((day.x - 1.0).abs() < 1e-5);
+        assert_eq!(night, Vec3::zeros());
+        assert!(terminator.x.abs() < 1e-5);
+    }
+
+    #[test]
+    fn only_two_sided_surfaces_flip_toward_the_eye() {
+        let lighting = sun();
+        let material = Material::default();
+        //borde de noche de una esfera cerrada visto desde el lado del sol:
+        //la normal interpolada se aleja de la camara y no debe recibir luz
+        let limb = Vec3::new(5.0, 0.0, 1.0);
+        let normal = Vec3::new(1.0, 0.0, 0.2);
+        let eye = Vec3::new(2.0, 0.0, 10.0);
+
+        let (culled, _) = lighting.light_at(&limb, &normal, &eye, &material, false);
+        let (ring, _) = lighting.light_at(&limb, &normal, &eye, &material, true);
+        assert_eq!(culled, Vec3::zeros());
+        assert!(ring.x > 0.0);
+    }
+
+    #[test]
+    fn blinn_phong_highlight_peaks_at_mirror_direction() {
+        let lighting = Lighting::new(vec![Light::Directional {
+            direction: Vec3::new(0.0, 0.0, -1.0),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+        }]);
+        let material = Material { specular: 1.0, shininess: 16.0 };
+        let position = Vec3::zeros();
+        let normal = Vec3::z();
+
+        let (_, facing) = lighting.light_at(&position, &normal, &Vec3::new(0.0, 0.0, 5.0), &material, false);
+        let (_, grazing) = lighting.light_at(&position, &normal, &Vec3::new(5.0, 0.0, 1.0), &material, false);
+        assert!((facing.x - 1.0).abs() < 1e-5);
+        assert!(grazing.x < facing.x);
+    }
+
+    #[test]
+    fn ambient_reaches_everything() {
+        let lighting = Lighting::new(vec![Light::Ambient { color: Vec3::new(1.0, 0.5, 0.0), intensity: 0.1 }]);
+        let (diffuse, specular) =
+            lighting.light_at(&Vec3::zeros(), &Vec3::y(), &Vec3::new(0.0, -3.0, 0.0), &Material::default(), false);
+        assert!((diffuse - Vec3::new(0.1, 0.05, 0.0)).norm() < 1e-6);
+        assert_eq!(specular, Vec3::zeros());
+    }
+}
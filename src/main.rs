@@ -1,50 +1,83 @@
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
-use minifb::{Key, Window, WindowOptions};
-use std::time::Duration;
-use std::f32::consts::PI;
+use nalgebra_glm::{Vec3, Vec4, Mat4, look_at, perspective};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use std::time::{Duration, Instant};
 
 mod framebuffer;
+mod antialiasing;
+mod hdr;
+mod postprocess;
+mod preset;
 mod triangle;
 mod vertex;
 mod obj;
+mod mesh;
 mod color;
 mod fragment;
 mod shaders;
 mod camera;
+mod camera_rig;
+mod camera_track;
 mod audio;
 mod spaceship;
+mod controls;
+mod headless;
+mod scene;
+mod scene_graph;
+mod orbit;
+mod nbody;
+mod clock;
+mod clipping;
+mod culling;
+mod collision;
+mod bvh;
+mod tiles;
+mod lighting;
+mod starfield;
+mod texture;
+#[cfg(test)]
+mod golden;
 
 use framebuffer::Framebuffer;
+use antialiasing::Antialiasing;
 use vertex::Vertex;
-use obj::Obj;
-use camera::Camera;
-use triangle::triangle;
-use shaders::{vertex_shader, fragment_shader};
+use triangle::{triangle, ScreenRect};
+use tiles::{render_tiles, ShadingTarget};
+use clipping::{clip_triangle, to_screen, Clipped};
+use mesh::Mesh;
+use culling::{bounding_sphere, is_back_face, FaceCulling, Frustum};
+use shaders::{ShaderParams, ShaderProgram, ShaderRegistry};
+use lighting::{Lighting, Shading};
+use texture::TextureSet;
 use fastnoise_lite::{FastNoiseLite, NoiseType};
-use rand::Rng;
 use audio::AudioPlayer;
-use spaceship::Spaceship;
-
-//planetas
-#[derive(PartialEq)]
-enum Planet{
-    None
-}
-
-struct Orbit {
-    //Distancia sol
-    radius: f32, 
-    speed: f32,   
-    angle: f32
-}
+use clock::SimulationClock;
+use controls::Action;
+use camera_rig::CameraMode;
+use spaceship::ShipInput;
+use bvh::Ray;
+use headless::HeadlessOptions;
+use preset::Preset;
+use scene::{PhysicsMode, Scene, SceneDescription, DEFAULT_SCENE};
+use scene_graph::NodeId;
 
 pub struct Uniforms {
     model_matrix: Mat4,
     view_matrix: Mat4,
     projection_matrix: Mat4,
     viewport_matrix: Mat4,
-    time: u32,
-    noise: FastNoiseLite
+    //segundos simulados, se congelan en pausa y retroceden con escala negativa
+    time: f32,
+    noise: FastNoiseLite,
+    params: ShaderParams,
+    //luces de la escena en mundo y posicion de la camara (especular)
+    lighting: Lighting,
+    camera_position: Vec3,
+    //mapas del objeto que se esta dibujando
+    textures: TextureSet,
+    //luz por fragmento o por vertice
+    shading: Shading,
+    //se dibuja sin culling: la luz usa la cara que mira a la camara
+    two_sided: bool,
 }
 
 fn create_noise() -> FastNoiseLite {
@@ -100,13 +133,10 @@ fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
     look_at(&eye, &center, &up)
 }
 
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
+fn create_perspective_matrix(window_width: f32, window_height: f32, fov: f32) -> Mat4 {
     let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
 
-    perspective(fov, aspect_ratio, near, far)
+    perspective(fov, aspect_ratio, camera::NEAR, camera::FAR)
 }
 
 fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
@@ -118,70 +148,245 @@ fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     )
 }
 
-//limites
-fn collision(position: Vec3, planet_position: Vec3, planet_radius: f32) -> bool{
-    let distance = (position - planet_position).norm();
-    distance < planet_radius
-}
-
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader_type: &str) {
-    // Vertex Shader
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+fn render(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    mesh: &Mesh,
+    shader: &ShaderProgram,
+    culling: FaceCulling,
+) {
+    // Vertex Shader: una sola vez por vertice unico de la malla
+    let mut vertices: Vec<Vertex> = mesh
+        .vertices
+        .iter()
+        .map(|vertex| shader.vertex.shade(vertex, uniforms))
+        .collect();
+
+    // Primitive Assembly + Clipping contra el frustum en espacio homogeneo; los
+    // vertices que crea el recorte se agregan al final del buffer
+    let mut triangles: Vec<[usize; 3]> = Vec::with_capacity(mesh.triangle_count());
+    for [a, b, c] in mesh.triangles() {
+        let [a, b, c] = [a as usize, b as usize, c as usize];
+        match clip_triangle([&vertices[a], &vertices[b], &vertices[c]]) {
+            Clipped::Inside => triangles.push([a, b, c]),
+            Clipped::Outside => {}
+            Clipped::Polygon(polygon) => {
+                let first = vertices.len();
+                let count = polygon.len();
+                vertices.extend(polygon);
+                triangles.extend((1..count - 1).map(|i| [first, first + i, first + i + 1]));
+            }
+        }
     }
 
-    // Primitive Assembly
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    // division por w y viewport, luego descarte de caras traseras
+    for vertex in vertices.iter_mut() {
+        //detras de la camara: solo lo usan triangulos ya recortados
+        if vertex.clip_position.w > 0.0 {
+            to_screen(vertex, &uniforms.viewport_matrix);
         }
     }
-
-    // Rasterization
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
+    if culling == FaceCulling::Back {
+        triangles.retain(|&[a, b, c]| {
+            !is_back_face(&vertices[a].transformed_position, &vertices[b].transformed_position, &vertices[c].transformed_position)
+        });
     }
 
-    // Fragment Processing
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
+    // Rasterization + Fragment Processing por tiles en paralelo
+    if framebuffer.threads > 1 {
+        render_tiles(framebuffer, &vertices, &triangles, uniforms, shader, framebuffer.threads);
+        return;
+    }
 
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = fragment_shader(&fragment, &uniforms, shader_type);
-            let color = shaded_color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
-        }
+    // Rasterization + Fragment Processing: early-Z y directo al fragment shader
+    let screen = ScreenRect::new(framebuffer.width, framebuffer.height);
+    let mut target = ShadingTarget {
+        rect: screen,
+        pattern: framebuffer.antialiasing().pattern(),
+        color: &mut framebuffer.samples,
+        depth: &mut framebuffer.zbuffer,
+        uniforms,
+        shader,
+    };
+    for &[a, b, c] in &triangles {
+        triangle(&vertices[a], &vertices[b], &vertices[c], &screen, &mut target);
     }
 }
 
-fn render_background(framebuffer: &mut Framebuffer, num_stars: u32) {
-    let mut rng = rand::thread_rng();
+pub fn render_frame(framebuffer: &mut Framebuffer, scene: &Scene, time: f32) {
+    let camera = &scene.camera;
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+    let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+    let projection_matrix = create_perspective_matrix(width, height, camera.fov);
+    let frustum = Frustum::from_matrix(&(projection_matrix * view_matrix));
+    let lighting = scene.lighting();
+
+    //el cielo esta en el infinito: solo lo mueve el giro de la camara
+    let mut sky_view = view_matrix;
+    sky_view.set_column(3, &Vec4::w());
+    let (buffer_width, buffer_height) = (framebuffer.width, framebuffer.height);
+    scene
+        .starfield
+        .draw(framebuffer.background_mut(), buffer_width, buffer_height, &(projection_matrix * sky_view), time);
+    framebuffer.clear();
+
+    //recorre el grafo: sol, planetas, lunas, anillos y la nave
+    for node in scene.graph.nodes() {
+        let (Some(renderable), Some(model_matrix)) = (&node.renderable, node.mesh_matrix()) else {
+            continue;
+        };
+        //fuera de camara: ni siquiera pasa por el vertex shader
+        let (center, radius) = bounding_sphere(&model_matrix, &renderable.model.center, renderable.model.radius);
+        if !frustum.contains_sphere(&center, radius) {
+            continue;
+        }
+        let uniforms = Uniforms {
+            model_matrix,
+            view_matrix,
+            projection_matrix,
+            viewport_matrix: create_viewport_matrix(width, height),
+            time,
+            noise: create_noise(),
+            params: renderable.params.clone(),
+            lighting: lighting.clone(),
+            camera_position: camera.eye,
+            textures: renderable.textures.clone(),
+            shading: scene.preset.shading(),
+            two_sided: renderable.culling == FaceCulling::None,
+        };
+
+        render(framebuffer, &uniforms, &renderable.model.mesh, &renderable.shader, renderable.culling);
+    }
 
-    for _ in 0..num_stars {
-        let x = rng.gen_range(0..framebuffer.width);
-        let y = rng.gen_range(0..framebuffer.height);
+    framebuffer.resolve();
+    scene.post.apply_linear(framebuffer);
+    hdr::present(framebuffer, &scene.hdr);
+    scene.post.apply(framebuffer, time);
+}
 
-        framebuffer.set_background_star(x, y, 0xFFFFFF);
+//quita `--nombre valor` de los argumentos; sin valor es un error de uso
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == name)?;
+    args.remove(index);
+    if index < args.len() && !args[index].starts_with("--") {
+        Some(args.remove(index))
+    } else {
+        eprintln!("{} requires a value", name);
+        std::process::exit(2);
     }
 }
+
 fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    let scene_path = take_option(&mut args, "--scene").unwrap_or_else(|| DEFAULT_SCENE.to_string());
+    let physics_mode = match take_option(&mut args, "--physics") {
+        Some(name) => match PhysicsMode::from_name(&name) {
+            Some(mode) => Some(mode),
+            None => {
+                eprintln!("unknown physics mode: {} (kepler, nbody)", name);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let threads = match take_option(&mut args, "--threads").map(|value| value.parse::<usize>()) {
+        Some(Ok(threads)) if threads > 0 => Some(threads),
+        Some(_) => {
+            eprintln!("--threads requires a positive number");
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let preset = match take_option(&mut args, "--preset") {
+        Some(name) => match Preset::from_name(&name) {
+            Some(preset) => Some(preset),
+            None => {
+                eprintln!("unknown preset: {} ({})", name, preset::NAMES);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let camera_mode = match take_option(&mut args, "--camera") {
+        Some(name) => match CameraMode::from_name(&name) {
+            Some(mode) => Some(mode),
+            None => {
+                eprintln!("unknown camera mode: {} ({})", name, camera_rig::NAMES);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let track = take_option(&mut args, "--track");
+    let antialiasing = match take_option(&mut args, "--aa") {
+        Some(name) => match Antialiasing::from_name(&name) {
+            Some(antialiasing) => antialiasing,
+            None => {
+                eprintln!("unknown anti-aliasing mode: {} ({})", name, antialiasing::NAMES);
+                std::process::exit(2);
+            }
+        },
+        None => Antialiasing::None,
+    };
+    let headless_options = match HeadlessOptions::from_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n{}", err, headless::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let shaders = ShaderRegistry::default();
+    let scene = SceneDescription::load(&scene_path).and_then(|mut description| {
+        if let Some(mode) = physics_mode {
+            description.physics.mode = mode;
+        }
+        if let Some(preset) = preset {
+            description.render.preset = preset;
+        }
+        //--track reproduce el recorrido salvo que --camera pida otro modo
+        if let Some(track) = track {
+            description.camera.track = Some(track);
+            description.camera.mode = CameraMode::Track;
+        }
+        if let Some(mode) = camera_mode {
+            description.camera.mode = mode;
+        }
+        Scene::build(&description, &shaders)
+    });
+    let mut scene = match scene {
+        Ok(scene) => scene,
+        Err(err) => {
+            eprintln!("failed to load scene {}: {}", scene_path, err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(options) = headless_options {
+        if let Err(err) = headless::run(&options, &mut scene, threads, antialiasing) {
+            eprintln!("headless render failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let window_width = 800;
     let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
-    let frame_delay = Duration::from_millis(16);
+    //con el preset n64 se dibuja a menos resolucion y se agranda al mostrar
+    let (framebuffer_width, framebuffer_height) = scene.preset.resolution().unwrap_or((window_width, window_height));
+    //presupuesto por frame (~60 fps); la simulacion no depende de el
+    let frame_budget = Duration::from_millis(16);
+    //cuanto se muestra un choque en el titulo
+    let collision_title_time = Duration::from_secs(3);
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    if let Some(threads) = threads {
+        framebuffer.threads = threads;
+    }
+    framebuffer.set_antialiasing(antialiasing);
+    let mut display = ((framebuffer_width, framebuffer_height) != (window_width, window_height))
+        .then(|| Framebuffer::new(window_width, window_height));
     let mut window = Window::new(
         "Space Travel",
         window_width,
@@ -193,153 +398,151 @@ fn main() {
     window.set_position(200, 40);
     window.update();
 
-    framebuffer.set_background_color(0x151515);
-
     // Música
     let audio_player = AudioPlayer::new("assets/music/September.mp3");
     audio_player.play();
 
-    // Nave
-    let mut spaceship = Spaceship::new(Vec3::new(0.0, 0.0, 4.0));
-
-    // Cámara
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 5.0, -20.0),
-        spaceship.position,
-        Vec3::new(0.0, 1.0, 0.0),
-    );
-    
-    let sun_position = Vec3::new(0.0, 0.0, 0.0);
-    // Movumintos planetas y posiciones
-    let mut orbits = vec![
-        Orbit { radius: 4.0, speed: 0.003, angle: 2.0 }, 
-        Orbit { radius: 7.0, speed: 0.01, angle: 0.0 },
-        Orbit { radius: 9.0, speed: 0.004, angle: 0.0 },
-        Orbit { radius: 12.0, speed: 0.009, angle: 0.0 }
-    ];
-    
-    let mut planet_positions = vec![
-        Vec3::new(4.1, 0.0, -2.3),
-        Vec3::new(-5.0, -0.1, 2.5),
-        Vec3::new(2.1, 0.0, -4.4), 
-        Vec3::new(-1.0, 0.1, 5.0)
-    ];
-    
-    let num_stars = 80;
-    render_background(&mut framebuffer, num_stars);
-
-    //modelos
-    let obj = Obj::load("assets/models/sphere.obj").expect("Failed to load obj");
-    let vertex_arrays = obj.get_vertex_array();
-    let ship = Obj::load("assets/models/nave.obj").expect("Failed to load obj");
-    let ship_vertex_arrays = ship.get_vertex_array();
-    let mut time = 0;
+    let mut clock = SimulationClock::new();
+    let mut frame: u32 = 0;
+    //ultimo choque de la nave (cuerpo, velocidad de impacto y cuando), se ve en el titulo
+    let mut last_collision: Option<(NodeId, f32, Instant)> = None;
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
 
-        time += 1;
-
-        handle_input(&window, &mut spaceship, &mut camera,  &planet_positions);
-
-        framebuffer.clear();
+        let frame_start = Instant::now();
+        frame += 1;
+        let (real_seconds, simulated_seconds) = clock.tick();
 
-        //sol, centro sistema
-        let sun_model_matrix = create_model_matrix(sun_position, 1.5, Vec3::new(0.0, 0.0, 0.0)); // Tamaño 1.5
-        let sun_uniforms = Uniforms {
-            model_matrix: sun_model_matrix,
-            view_matrix: create_view_matrix(camera.eye, camera.center, camera.up),
-            projection_matrix: create_perspective_matrix(window_width as f32, window_height as f32),
-            viewport_matrix: create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32),
-            time,
-            noise: create_noise(),
-        };
+        handle_clock_keys(&window, &mut clock);
+        handle_exposure_keys(&window, &mut scene);
+        handle_post_keys(&window, &mut scene);
+        let camera_input = handle_input(&window, &mut scene, real_seconds);
 
-        for (i, orbit) in orbits.iter_mut().enumerate() {
-            orbit.angle += orbit.speed; //angfulo orbita
-            let x = sun_position.x + orbit.radius * orbit.angle.cos();
-            let z = sun_position.z + orbit.radius * orbit.angle.sin();
-            planet_positions[i] = Vec3::new(x, 0.0, z); //nueva posiicon
+        scene.update(simulated_seconds);
+        //la camara va en tiempo real: se puede mover con la simulacion en pausa
+        scene.update_camera(&camera_input, real_seconds);
+        if let Some(event) = scene.take_collisions().into_iter().max_by(|a, b| a.speed.total_cmp(&b.speed)) {
+            last_collision = Some((event.node, event.speed, frame_start));
         }
-
-        render(&mut framebuffer, &sun_uniforms, &vertex_arrays, "lava_shader");
-
-        //planetas
-        for (i, &position) in planet_positions.iter().enumerate() {
-            let model_matrix = create_model_matrix(position, 1.0, Vec3::new(0.0, 0.0, 0.0));
-            let planet_shader = match i {
-                0 => "continents_shader",
-                1 => "another_shader",
-                2 => "gradient_shader",
-                3 => "lines_shader",
-                _ => "default_shader",
+        render_frame(&mut framebuffer, &scene, clock.elapsed());
+
+        //escala de tiempo y, en modo nbody, energia y momento en el titulo
+        if frame.is_multiple_of(30) {
+            let mut title = if clock.is_paused() {
+                "Space Travel - pausa".to_string()
+            } else {
+                format!("Space Travel - x{}", clock.time_scale())
             };
-
-            let uniforms = Uniforms {
-                model_matrix,
-                view_matrix: create_view_matrix(camera.eye, camera.center, camera.up),
-                projection_matrix: create_perspective_matrix(window_width as f32, window_height as f32),
-                viewport_matrix: create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32),
-                time,
-                noise: create_noise(),
-            };
-
-            render(&mut framebuffer, &uniforms, &vertex_arrays, planet_shader);
+            title += &format!(" - exp {:.2}", scene.hdr.exposure);
+            let camera_mode = scene.camera_rig.mode();
+            title += &format!(" - cam {}", camera_mode.name());
+            if let Some(target) = scene.camera_rig.target_name().filter(|_| camera_mode.needs_target()) {
+                title += &format!(" ({})", target);
+            }
+            //cuerpo mas cercano y el que esta delante de la nariz
+            if let Some((body, distance)) = scene.nearest_body(&scene.spaceship.position) {
+                title += &format!(" - cerca {} {:.1}", scene.graph.node(body).name, distance);
+            }
+            let nose = Ray::new(scene.spaceship.position, scene.spaceship.forward());
+            if let Some((body, distance)) = scene.ray_cast(&nose, 100.0) {
+                title += &format!(" - frente {} {:.1}", scene.graph.node(body).name, distance);
+            }
+            if let Some((body, speed, _)) = last_collision.filter(|(_, _, at)| at.elapsed() < collision_title_time) {
+                title += &format!(" - choque {} {:.1}", scene.graph.node(body).name, speed);
+            }
+            let effects: Vec<&str> = scene.post.enabled().map(|effect| effect.name()).collect();
+            if !effects.is_empty() {
+                title += &format!(" - {}", effects.join("+"));
+            }
+            if let Some(gravity) = scene.gravity.as_ref() {
+                let (diagnostics, drift) = gravity.diagnostics();
+                title += &format!(
+                    " - E {:.4} (drift {:+.2e}) |p| {:.3e} |L| {:.4}",
+                    diagnostics.total_energy(),
+                    drift,
+                    diagnostics.momentum.norm(),
+                    diagnostics.angular_momentum.norm()
+                );
+            }
+            window.set_title(&title);
         }
 
-        //Render nave
-        let model_matrix = create_model_matrix(spaceship.position, 1.0, spaceship.forward);
-        let uniforms = Uniforms {
-            model_matrix,
-            view_matrix: create_view_matrix(camera.eye, camera.center, camera.up),
-            projection_matrix: create_perspective_matrix(window_width as f32, window_height as f32),
-            viewport_matrix: create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32),
-            time,
-            noise: create_noise(),
+        let shown = match display.as_mut() {
+            Some(display) => {
+                framebuffer.upscale_to(display);
+                display
+            }
+            None => &framebuffer,
         };
-        render(&mut framebuffer, &uniforms, &ship_vertex_arrays, "spaceship_shader");
-
         window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
+            .update_with_buffer(&shown.buffer, shown.width, shown.height)
             .unwrap();
 
-        std::thread::sleep(frame_delay);
+        //solo duerme lo que sobra del frame
+        std::thread::sleep(frame_budget.saturating_sub(frame_start.elapsed()));
     }
 }
 
-fn handle_input(window: &Window, spaceship: &mut Spaceship, camera: &mut Camera, planet_positions: &[Vec3]) {
-    let movement_speed = 0.1;
-    let rotation_speed = 0.1;
-    let planet_radius = 0.9;
-    // Movimiento de la nave
-    if window.is_key_down(Key::Up) {
-        let new_position = spaceship.position - spaceship.forward * movement_speed;
-        if !planet_positions.iter().any(|&planet_position| collision(new_position, planet_position, planet_radius)) {
-            spaceship.move_forward(-movement_speed);
-        }
+//P pausa, R invierte el tiempo y 1-4 eligen la escala
+fn handle_clock_keys(window: &Window, clock: &mut SimulationClock) {
+    if window.is_key_pressed(Key::P, KeyRepeat::No) {
+        clock.toggle_pause();
+    }
+    if window.is_key_pressed(Key::R, KeyRepeat::No) {
+        clock.reverse();
     }
-    if window.is_key_down(Key::Down) {
-        let new_position = spaceship.position + spaceship.forward * movement_speed;
-        if !planet_positions.iter().any(|&planet_position| collision(new_position, planet_position, planet_radius)) {
-            spaceship.move_forward(movement_speed);
+    let presets = [(Key::Key1, 0.1), (Key::Key2, 1.0), (Key::Key3, 10.0), (Key::Key4, 1000.0)];
+    for (key, scale) in presets {
+        if window.is_key_pressed(key, KeyRepeat::No) {
+            //conserva el sentido si el tiempo iba hacia atras
+            clock.set_time_scale(scale * clock.time_scale().signum());
         }
     }
-    //giro
-    if window.is_key_down(Key::Right) {
-        spaceship.rotate(-rotation_speed); 
+}
+
+//- y = bajan y suben la exposicion medio paso
+fn handle_exposure_keys(window: &Window, scene: &mut Scene) {
+    if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+        scene.hdr.exposure /= 2f32.sqrt();
     }
-    if window.is_key_down(Key::Left) {
-        spaceship.rotate( rotation_speed);
+    if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+        scene.hdr.exposure *= 2f32.sqrt();
     }
+}
 
-    let mut movement = Vec3::new(0.0, 0.0, 0.0); // Movimiento 3D
-    // Verificacion colisiones
-    let new_camera_position = camera.eye + movement;
-    if !planet_positions.iter().any(|&planet_position| collision(new_camera_position, planet_position, planet_radius)) {
-        camera.eye = new_camera_position;
+//F1..F9 prenden y apagan los efectos de post-proceso, en el orden de la escena
+fn handle_post_keys(window: &Window, scene: &mut Scene) {
+    let keys = [Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9];
+    for (index, key) in keys.into_iter().enumerate() {
+        if window.is_key_pressed(key, KeyRepeat::No) {
+            scene.post.toggle(index);
+        }
+    }
+}
+
+//los controles de la nave van en tiempo real, no se escalan ni se pausan; en
+//las camaras orbit y free mueven la camara y se devuelven para ella
+fn handle_input(window: &Window, scene: &mut Scene, seconds: f32) -> ShipInput {
+    let pressed = |action| window.is_key_pressed(scene.controls.key(action), KeyRepeat::No);
+    let (coast, next_camera, next_target) = (pressed(Action::Coast), pressed(Action::NextCamera), pressed(Action::NextTarget));
+    if coast {
+        scene.spaceship.flight.coasting = !scene.spaceship.flight.coasting;
+    }
+    if next_camera {
+        scene.camera_rig.next_mode();
+    }
+    if next_target {
+        scene.camera_rig.next_target();
     }
-    camera.center = spaceship.position;
-    camera.eye = spaceship.position + spaceship.forward * 10.0;
+    let input = scene.controls.input(|key| window.is_key_down(key));
+    let (ship_input, camera_input) = if scene.camera_rig.mode().drives_ship() {
+        (input, ShipInput::default())
+    } else {
+        (ShipInput::default(), input)
+    };
+    scene.fly_ship(&ship_input, seconds);
+    camera_input
 }
\ No newline at end of file
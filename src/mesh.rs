@@ -0,0 +1,247 @@
+use nalgebra_glm::{Vec3, Vec4};
+
+use crate::vertex::Vertex;
+
+//Malla indexada: cada vertice unico una sola vez y tres indices por triangulo,
+//asi el vertex shader corre una vez por vertice y no una por esquina
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+//tamano de cache que se asume al reordenar (cache de GPU tipica)
+const VERTEX_CACHE_SIZE: usize = 32;
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        assert!(indices.len().is_multiple_of(3), "index buffer must hold whole triangles");
+        assert!(
+            indices.iter().all(|&index| (index as usize) < vertices.len()),
+            "index out of range"
+        );
+        Mesh { vertices, indices }
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]])
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    //Tangentes por vertice a partir de las coordenadas de textura (metodo de
+    //Lengyel): hacen falta para los normal maps
+    pub fn compute_tangents(&mut self) {
+        let mut u_directions = vec![Vec3::zeros(); self.vertices.len()];
+        let mut v_directions = vec![Vec3::zeros(); self.vertices.len()];
+        for [a, b, c] in self.triangles() {
+            let [a, b, c] = [a as usize, b as usize, c as usize];
+            let (va, vb, vc) = (&self.vertices[a], &self.vertices[b], &self.vertices[c]);
+            let (edge1, edge2) = (vb.position - va.position, vc.position - va.position);
+            let (uv1, uv2) = (vb.tex_coords - va.tex_coords, vc.tex_coords - va.tex_coords);
+            let determinant = uv1.x * uv2.y - uv2.x * uv1.y;
+            //sin coordenadas de textura (o degeneradas) no aporta
+            if determinant.abs() < f32::EPSILON {
+                continue;
+            }
+            let u_direction = (edge1 * uv2.y - edge2 * uv1.y) / determinant;
+            let v_direction = (edge2 * uv1.x - edge1 * uv2.x) / determinant;
+            for index in [a, b, c] {
+                u_directions[index] += u_direction;
+                v_directions[index] += v_direction;
+            }
+        }
+
+        for (vertex, (u_direction, v_direction)) in self.vertices.iter_mut().zip(u_directions.iter().zip(&v_directions)) {
+            let normal = vertex.normal;
+            //Gram-Schmidt: perpendicular a la normal
+            let tangent = (u_direction - normal * normal.dot(u_direction))
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(|| any_perpendicular(&normal));
+            let handedness = if normal.cross(&tangent).dot(v_direction) < 0.0 { -1.0 } else { 1.0 };
+            vertex.tangent = Vec4::new(tangent.x, tangent.y, tangent.z, handedness);
+        }
+    }
+
+    //vertices transformados por triangulo con una cache FIFO de `cache_size`
+    //(1.0 = ningun vertice se reutiliza, ~0.5 es lo mejor posible)
+    pub fn acmr(&self, cache_size: usize) -> f32 {
+        if self.indices.is_empty() {
+            return 0.0;
+        }
+        let mut cache = std::collections::VecDeque::with_capacity(cache_size);
+        let mut misses = 0;
+        for &index in &self.indices {
+            if !cache.contains(&index) {
+                misses += 1;
+                if cache.len() == cache_size {
+                    cache.pop_front();
+                }
+                cache.push_back(index);
+            }
+        }
+        misses as f32 / self.triangle_count() as f32
+    }
+
+    //Reordena los triangulos para reutilizar vertices recien transformados
+    //(algoritmo de Tom Forsyth, "Linear-Speed Vertex Cache Optimisation").
+    //No cambia la geometria, solo el orden de los indices.
+    pub fn optimize_vertex_cache(&mut self) {
+        let triangle_count = self.triangle_count();
+        if triangle_count == 0 {
+            return;
+        }
+
+        let mut vertex_triangles = vec![Vec::new(); self.vertices.len()];
+        for (triangle, corners) in self.indices.chunks_exact(3).enumerate() {
+            for &vertex in corners {
+                vertex_triangles[vertex as usize].push(triangle);
+            }
+        }
+        let mut vertex_scores: Vec<f32> = vertex_triangles
+            .iter()
+            .map(|triangles| vertex_score(None, triangles.len()))
+            .collect();
+        let triangle_score = |corners: &[u32], scores: &[f32]| -> f32 {
+            corners.iter().map(|&vertex| scores[vertex as usize]).sum()
+        };
+
+        let mut added = vec![false; triangle_count];
+        let mut cache: Vec<u32> = Vec::with_capacity(VERTEX_CACHE_SIZE + 3);
+        let mut output = Vec::with_capacity(self.indices.len());
+        let mut best = None;
+
+        for _ in 0..triangle_count {
+            //si la cache no ofrece candidatos se busca en toda la malla
+            let triangle = best.unwrap_or_else(|| {
+                (0..triangle_count)
+                    .filter(|&triangle| !added[triangle])
+                    .max_by(|&a, &b| {
+                        let score_a = triangle_score(&self.indices[a * 3..a * 3 + 3], &vertex_scores);
+                        let score_b = triangle_score(&self.indices[b * 3..b * 3 + 3], &vertex_scores);
+                        score_a.total_cmp(&score_b)
+                    })
+                    .expect("a triangle is left")
+            });
+            added[triangle] = true;
+            let corners = [
+                self.indices[triangle * 3],
+                self.indices[triangle * 3 + 1],
+                self.indices[triangle * 3 + 2],
+            ];
+            output.extend_from_slice(&corners);
+
+            //los vertices del triangulo pasan al frente de la cache (LRU)
+            for &vertex in &corners {
+                vertex_triangles[vertex as usize].retain(|&other| other != triangle);
+                cache.retain(|&cached| cached != vertex);
+            }
+            cache.splice(0..0, corners);
+            let evicted: Vec<u32> = cache.drain(cache.len().min(VERTEX_CACHE_SIZE)..).collect();
+
+            for &vertex in &evicted {
+                vertex_scores[vertex as usize] = vertex_score(None, vertex_triangles[vertex as usize].len());
+            }
+            for (position, &vertex) in cache.iter().enumerate() {
+                vertex_scores[vertex as usize] =
+                    vertex_score(Some(position), vertex_triangles[vertex as usize].len());
+            }
+
+            //el siguiente es el mejor triangulo que toca la cache
+            best = cache
+                .iter()
+                .flat_map(|&vertex| vertex_triangles[vertex as usize].iter().copied())
+                .max_by(|&a, &b| {
+                    let score_a = triangle_score(&self.indices[a * 3..a * 3 + 3], &vertex_scores);
+                    let score_b = triangle_score(&self.indices[b * 3..b * 3 + 3], &vertex_scores);
+                    score_a.total_cmp(&score_b)
+                });
+        }
+
+        self.indices = output;
+    }
+}
+
+fn any_perpendicular(normal: &Vec3) -> Vec3 {
+    let axis = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+    normal.cross(&axis).try_normalize(f32::EPSILON).unwrap_or(Vec3::x())
+}
+
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    const CACHE_DECAY_POWER: f32 = 1.5;
+    const LAST_TRIANGLE_SCORE: f32 = 0.75;
+    const VALENCE_BOOST_SCALE: f32 = 2.0;
+    const VALENCE_BOOST_POWER: f32 = 0.5;
+
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        None => 0.0,
+        //los tres del ultimo triangulo valen un poco menos, para no girar en el lugar
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scale = 1.0 / (VERTEX_CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(CACHE_DECAY_POWER)
+        }
+    };
+    cache_score + VALENCE_BOOST_SCALE * (remaining_triangles as f32).powf(-VALENCE_BOOST_POWER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    //rejilla de n x n cuadros con los triangulos desordenados
+    fn shuffled_grid(n: u32) -> Mesh {
+        let vertices = vec![Vertex::default(); ((n + 1) * (n + 1)) as usize];
+        let mut triangles = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                triangles.push([i, i + 1, i + n + 1]);
+                triangles.push([i + 1, i + n + 2, i + n + 1]);
+            }
+        }
+        triangles.shuffle(&mut StdRng::seed_from_u64(7));
+        Mesh::new(vertices, triangles.concat())
+    }
+
+    #[test]
+    fn optimization_keeps_triangles_and_lowers_acmr() {
+        let mut mesh = shuffled_grid(24);
+        let before = mesh.acmr(VERTEX_CACHE_SIZE);
+        let mut original: Vec<[u32; 3]> = mesh.triangles().collect();
+
+        mesh.optimize_vertex_cache();
+        let after = mesh.acmr(VERTEX_CACHE_SIZE);
+        let mut reordered: Vec<[u32; 3]> = mesh.triangles().collect();
+
+        original.sort();
+        reordered.sort();
+        assert_eq!(original, reordered);
+        assert!(after < 0.8 && after < before * 0.5, "acmr {} -> {}", before, after);
+    }
+
+    #[test]
+    fn tangents_follow_texture_u() {
+        use nalgebra_glm::Vec2;
+        //cuadro en el plano xy mirando a +z, con u hacia +x y v hacia -y
+        let corner = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.0), Vec3::z(), Vec2::new(x, 1.0 - y));
+        let mut mesh = Mesh::new(
+            vec![corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)],
+            vec![0, 1, 2, 0, 2, 3],
+        );
+        mesh.compute_tangents();
+        for vertex in &mesh.vertices {
+            assert!((vertex.tangent.xyz() - Vec3::x()).norm() < 1e-6);
+            //normal x tangente = +y, contrario a v
+            assert_eq!(vertex.tangent.w, -1.0);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use nalgebra_glm::{DVec3, Vec3};
+use serde::Deserialize;
+
+//Simulacion de gravedad newtoniana entre todos los cuerpos, integrada en
+//f64. El paso fijo lo decide quien llama (ver `clock::FixedStep`); un paso
+//negativo integra hacia atras.
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Integrator {
+    //drift-kick-drift: posiciones a medio paso, una evaluacion de fuerzas por paso; simplectico
+    Leapfrog,
+    //velocity Verlet (kick-drift-kick), reusa las aceleraciones del final del paso anterior; simplectico
+    Verlet,
+    //Runge-Kutta de cuarto orden, no conserva energia a largo plazo
+    Rk4,
+}
+
+//Un cuerpo con masa 0 siente la gravedad pero no atrae (p. ej. la nave)
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub mass: f64,
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+impl Particle {
+    pub fn new(mass: f64, position: Vec3, velocity: Vec3) -> Self {
+        Particle {
+            mass,
+            position: to_dvec3(position),
+            velocity: to_dvec3(velocity),
+        }
+    }
+
+    pub fn position_f32(&self) -> Vec3 {
+        to_vec3(self.position)
+    }
+
+    pub fn velocity_f32(&self) -> Vec3 {
+        to_vec3(self.velocity)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostics {
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    pub momentum: DVec3,
+    pub angular_momentum: DVec3,
+}
+
+impl Diagnostics {
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy + self.potential_energy
+    }
+}
+
+pub struct NBody {
+    pub particles: Vec<Particle>,
+    pub gravitational_constant: f64,
+    //evita fuerzas infinitas en encuentros cercanos
+    pub softening: f64,
+    pub integrator: Integrator,
+    //masa, posicion y aceleracion de cada particula al final del ultimo paso
+    //de Verlet; se reusan al empezar el siguiente si nadie las movio
+    cache: Vec<(f64, DVec3, DVec3)>,
+}
+
+impl NBody {
+    pub fn new(gravitational_constant: f64, softening: f64, integrator: Integrator) -> Self {
+        NBody {
+            particles: Vec::new(),
+            gravitational_constant,
+            softening,
+            integrator,
+            cache: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, particle: Particle) -> usize {
+        self.particles.push(particle);
+        self.particles.len() - 1
+    }
+
+    pub fn step(&mut self, dt: f64) {
+        match self.integrator {
+            Integrator::Leapfrog => {
+                for particle in self.particles.iter_mut() {
+                    particle.position += particle.velocity * (0.5 * dt);
+                }
+                let accelerations = self.accelerations(&self.positions());
+                for (particle, acceleration) in self.particles.iter_mut().zip(&accelerations) {
+                    particle.velocity += acceleration * dt;
+                    particle.position += particle.velocity * (0.5 * dt);
+                }
+            }
+            Integrator::Verlet => {
+                let accelerations = self.start_accelerations();
+                for (particle, acceleration) in self.particles.iter_mut().zip(&accelerations) {
+                    particle.position += particle.velocity * dt + acceleration * (0.5 * dt * dt);
+                }
+                let new_accelerations = self.accelerations(&self.positions());
+                for ((particle, old), new) in self.particles.iter_mut().zip(&accelerations).zip(&new_accelerations) {
+                    particle.velocity += (old + new) * (0.5 * dt);
+                }
+                self.cache = self
+                    .particles
+                    .iter()
+                    .zip(new_accelerations)
+                    .map(|(particle, acceleration)| (particle.mass, particle.position, acceleration))
+                    .collect();
+            }
+            Integrator::Rk4 => self.step_rk4(dt),
+        }
+    }
+
+    fn step_rk4(&mut self, dt: f64) {
+        let x0 = self.positions();
+        let v0: Vec<DVec3> = self.particles.iter().map(|particle| particle.velocity).collect();
+
+        let offset = |base: &[DVec3], delta: &[DVec3], scale: f64| -> Vec<DVec3> {
+            base.iter().zip(delta).map(|(b, d)| b + d * scale).collect()
+        };
+
+        let a1 = self.accelerations(&x0);
+        let x2 = offset(&x0, &v0, 0.5 * dt);
+        let v2 = offset(&v0, &a1, 0.5 * dt);
+        let a2 = self.accelerations(&x2);
+        let x3 = offset(&x0, &v2, 0.5 * dt);
+        let v3 = offset(&v0, &a2, 0.5 * dt);
+        let a3 = self.accelerations(&x3);
+        let x4 = offset(&x0, &v3, dt);
+        let v4 = offset(&v0, &a3, dt);
+        let a4 = self.accelerations(&x4);
+
+        for (i, particle) in self.particles.iter_mut().enumerate() {
+            particle.position += (v0[i] + v2[i] * 2.0 + v3[i] * 2.0 + v4[i]) * (dt / 6.0);
+            particle.velocity += (a1[i] + a2[i] * 2.0 + a3[i] * 2.0 + a4[i]) * (dt / 6.0);
+        }
+    }
+
+    //aceleraciones al empezar un paso de Verlet: las del paso anterior, salvo
+    //que alguien haya cambiado particulas. Si solo cambiaron particulas sin
+    //masa (la nave) se recalculan esas; si no, todas
+    fn start_accelerations(&self) -> Vec<DVec3> {
+        let positions = self.positions();
+        if self.cache.len() != self.particles.len() {
+            return self.accelerations(&positions);
+        }
+        let changed: Vec<usize> = (0..self.particles.len())
+            .filter(|&i| (self.particles[i].mass, positions[i]) != (self.cache[i].0, self.cache[i].1))
+            .collect();
+        if changed.iter().any(|&i| self.particles[i].mass != 0.0 || self.cache[i].0 != 0.0) {
+            return self.accelerations(&positions);
+        }
+        let mut accelerations: Vec<DVec3> = self.cache.iter().map(|&(_, _, acceleration)| acceleration).collect();
+        for i in changed {
+            accelerations[i] = self.acceleration_at(positions[i], &positions, Some(i));
+        }
+        accelerations
+    }
+
+    fn positions(&self) -> Vec<DVec3> {
+        self.particles.iter().map(|particle| particle.position).collect()
+    }
+
+    fn accelerations(&self, positions: &[DVec3]) -> Vec<DVec3> {
+        positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| self.acceleration_at(position, positions, Some(i)))
+            .collect()
+    }
+
+    fn acceleration_at(&self, point: DVec3, positions: &[DVec3], skip: Option<usize>) -> DVec3 {
+        let softening2 = self.softening * self.softening;
+        let mut acceleration = DVec3::zeros();
+        for (j, (source, &source_position)) in self.particles.iter().zip(positions).enumerate() {
+            if Some(j) == skip || source.mass == 0.0 {
+                continue;
+            }
+            let delta = source_position - point;
+            let distance2 = delta.norm_squared() + softening2;
+            acceleration += delta * (self.gravitational_constant * source.mass / (distance2 * distance2.sqrt()));
+        }
+        acceleration
+    }
+
+    //aceleracion gravitatoria en un punto cualquiera
+    pub fn gravity_at(&self, point: Vec3) -> Vec3 {
+        to_vec3(self.acceleration_at(to_dvec3(point), &self.positions(), None))
+    }
+
+    pub fn diagnostics(&self) -> Diagnostics {
+        let mut diagnostics = Diagnostics {
+            kinetic_energy: 0.0,
+            potential_energy: 0.0,
+            momentum: DVec3::zeros(),
+            angular_momentum: DVec3::zeros(),
+        };
+        let softening2 = self.softening * self.softening;
+
+        for (i, particle) in self.particles.iter().enumerate() {
+            if particle.mass == 0.0 {
+                continue;
+            }
+            diagnostics.kinetic_energy += 0.5 * particle.mass * particle.velocity.norm_squared();
+            diagnostics.momentum += particle.velocity * particle.mass;
+            diagnostics.angular_momentum += particle.position.cross(&(particle.velocity * particle.mass));
+
+            for other in &self.particles[i + 1..] {
+                let distance = ((other.position - particle.position).norm_squared() + softening2).sqrt();
+                diagnostics.potential_energy -= self.gravitational_constant * particle.mass * other.mass / distance;
+            }
+        }
+
+        diagnostics
+    }
+}
+
+fn to_dvec3(v: Vec3) -> DVec3 {
+    DVec3::new(v.x as f64, v.y as f64, v.z as f64)
+}
+
+fn to_vec3(v: DVec3) -> Vec3 {
+    Vec3::new(v.x as f32, v.y as f32, v.z as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //dos cuerpos en orbita circular alrededor de su centro de masa
+    fn binary(integrator: Integrator) -> NBody {
+        let mut system = NBody::new(1.0, 0.0, integrator);
+        let speed = 0.5f32.sqrt();
+        system.add(Particle::new(1.0, Vec3::new(-0.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -speed)));
+        system.add(Particle::new(1.0, Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 0.0, speed)));
+        system
+    }
+
+    #[test]
+    fn integrators_conserve_energy_and_momentum() {
+        for integrator in [Integrator::Leapfrog, Integrator::Verlet, Integrator::Rk4] {
+            let mut system = binary(integrator);
+            let start = system.diagnostics();
+            for _ in 0..2000 {
+                system.step(0.01);
+            }
+            let end = system.diagnostics();
+
+            let drift = ((end.total_energy() - start.total_energy()) / start.total_energy()).abs();
+            assert!(drift < 1e-4, "{:?} energy drift {}", integrator, drift);
+            assert!((end.momentum - start.momentum).norm() < 1e-9, "{:?}", integrator);
+            assert!((end.angular_momentum - start.angular_momentum).norm() < 1e-6, "{:?}", integrator);
+        }
+    }
+
+    #[test]
+    fn leapfrog_is_time_reversible() {
+        let mut system = binary(Integrator::Leapfrog);
+        let start = system.particles.clone();
+        for _ in 0..500 {
+            system.step(0.01);
+        }
+        for _ in 0..500 {
+            system.step(-0.01);
+        }
+        for (particle, initial) in system.particles.iter().zip(&start) {
+            assert!((particle.position - initial.position).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn verlet_reuses_accelerations_exactly() {
+        let mut system = binary(Integrator::Verlet);
+        let ship = system.add(Particle::new(0.0, Vec3::new(3.0, 0.0, 0.0), Vec3::zeros()));
+        for i in 0..100 {
+            //la nave se mueve desde afuera, como hace la escena entre pasos
+            if i % 10 == 0 {
+                system.particles[ship].position.y += 0.5;
+            }
+            let mut fresh = NBody::new(1.0, 0.0, Integrator::Verlet);
+            fresh.particles = system.particles.clone();
+            system.step(0.01);
+            fresh.step(0.01);
+            for (cached, recomputed) in system.particles.iter().zip(&fresh.particles) {
+                assert_eq!((cached.position, cached.velocity), (recomputed.position, recomputed.velocity));
+            }
+        }
+        //si cambia un cuerpo con masa se recalcula todo
+        system.particles[0].position.x -= 0.1;
+        let mut fresh = NBody::new(1.0, 0.0, Integrator::Verlet);
+        fresh.particles = system.particles.clone();
+        system.step(0.01);
+        fresh.step(0.01);
+        assert_eq!(system.particles[1].velocity, fresh.particles[1].velocity);
+    }
+
+    #[test]
+    fn massless_particle_feels_gravity_but_does_not_pull() {
+        let mut system = NBody::new(1.0, 0.0, Integrator::Leapfrog);
+        let star = system.add(Particle::new(10.0, Vec3::zeros(), Vec3::zeros()));
+        let ship = system.add(Particle::new(0.0, Vec3::new(5.0, 0.0, 0.0), Vec3::zeros()));
+        for _ in 0..10 {
+            system.step(0.01);
+        }
+        assert_eq!(system.particles[star].position, DVec3::zeros());
+        assert!(system.particles[ship].position.x < 5.0);
+    }
+}
@@ -1,12 +1,12 @@
-use tobj;
 use nalgebra_glm::{Vec2, Vec3};
+use crate::mesh::Mesh;
 use crate::vertex::Vertex;
 
 pub struct Obj {
-    meshes: Vec<Mesh>,
+    meshes: Vec<ObjMesh>,
 }
 
-struct Mesh {
+struct ObjMesh {
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
@@ -23,7 +23,7 @@ impl Obj {
 
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
-            Mesh {
+            ObjMesh {
                 vertices: mesh.positions.chunks(3)
                     .map(|v| Vec3::new(v[0], v[1], v[2]))
                     .collect(),
@@ -40,23 +40,29 @@ impl Obj {
         Ok(Obj { meshes })
     }
 
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+    //todas las partes del .obj en una sola malla indexada; tobj ya junta los
+    //vertices repetidos (`single_index`)
+    pub fn get_mesh(&self) -> Mesh {
         let mut vertices = Vec::new();
+        let mut indices = Vec::new();
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
+            let offset = vertices.len() as u32;
+            for (index, &position) in mesh.vertices.iter().enumerate() {
+                let normal = mesh.normals.get(index)
                     .cloned()
                     .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
+                let tex_coords = mesh.texcoords.get(index)
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
                 vertices.push(Vertex::new(position, normal, tex_coords));
             }
+            indices.extend(mesh.indices.iter().map(|&index| index + offset));
         }
 
-        vertices
+        let mut mesh = Mesh::new(vertices, indices);
+        mesh.compute_tangents();
+        mesh
     }
 }
\ No newline at end of file
@@ -0,0 +1,147 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use std::f64::consts::TAU;
+
+//Orbita kepleriana. Los angulos van en radianes; el plano de referencia es
+//xz (y hacia arriba), asi una orbita sin inclinacion queda en el plano de
+//los planetas.
+#[derive(Debug, Clone)]
+pub struct Orbit {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub longitude_of_ascending_node: f32,
+    pub argument_of_periapsis: f32,
+    pub mean_anomaly_at_epoch: f32,
+    //radianes de anomalia media por segundo simulado
+    pub mean_motion: f32,
+    //segundos simulados desde la epoca; en f64 porque crece sin limite
+    pub elapsed: f64,
+}
+
+impl Orbit {
+    pub fn circular(radius: f32, speed: f32, phase: f32) -> Self {
+        Orbit {
+            semi_major_axis: radius,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly_at_epoch: phase,
+            mean_motion: speed,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn advance(&mut self, seconds: f32) {
+        self.elapsed += seconds as f64;
+    }
+
+    //lo recorrido desde la epoca se reduce a una vuelta en f64 antes de
+    //pasar a f32, asi las corridas largas o aceleradas no tiemblan
+    pub fn mean_anomaly(&self) -> f32 {
+        self.mean_anomaly_at_epoch + (self.mean_motion as f64 * self.elapsed).rem_euclid(TAU) as f32
+    }
+
+    //posicion relativa al cuerpo central
+    pub fn position(&self) -> Vec3 {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let eccentric_anomaly = solve_kepler(self.mean_anomaly(), e);
+        let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+
+        //coordenadas en el plano de la orbita, x hacia el periapsis
+        let x = a * (cos_e - e);
+        let y = a * (1.0 - e * e).sqrt() * sin_e;
+
+        let (sin_w, cos_w) = self.argument_of_periapsis.sin_cos();
+        let (sin_o, cos_o) = self.longitude_of_ascending_node.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+
+        //rotacion Rz(nodo) * Rx(inclinacion) * Rz(periapsis)
+        let xw = x * cos_w - y * sin_w;
+        let yw = x * sin_w + y * cos_w;
+        let ecliptic_x = xw * cos_o - yw * cos_i * sin_o;
+        let ecliptic_y = xw * sin_o + yw * cos_i * cos_o;
+        let ecliptic_z = yw * sin_i;
+
+        //ecliptica con z arriba -> mundo con y arriba
+        Vec3::new(ecliptic_x, ecliptic_z, ecliptic_y)
+    }
+}
+
+//Resuelve M = E - e sin(E) con Newton-Raphson
+pub fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let m = (mean_anomaly + PI).rem_euclid(2.0 * PI) - PI;
+    let mut eccentric_anomaly = if eccentricity < 0.8 { m } else { PI.copysign(m) };
+
+    for _ in 0..30 {
+        let f = eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - m;
+        let step = f / (1.0 - eccentricity * eccentric_anomaly.cos());
+        eccentric_anomaly -= step;
+        if step.abs() < 1e-6 {
+            break;
+        }
+    }
+
+    eccentric_anomaly
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kepler_solution_satisfies_equation() {
+        for &e in &[0.0, 0.3, 0.7, 0.95] {
+            for i in 0..16 {
+                let m = -PI + i as f32 * (2.0 * PI / 16.0);
+                let ecc = solve_kepler(m, e);
+                assert!((ecc - e * ecc.sin() - m).abs() < 1e-4, "e = {}, M = {}", e, m);
+            }
+        }
+    }
+
+    #[test]
+    fn circular_orbit_matches_constant_angle() {
+        let mut orbit = Orbit::circular(4.0, 0.003, 2.0);
+        orbit.advance(100.0);
+        let angle: f32 = 2.0 + 0.003 * 100.0;
+        let expected = Vec3::new(4.0 * angle.cos(), 0.0, 4.0 * angle.sin());
+        assert!((orbit.position() - expected).norm() < 1e-4);
+    }
+
+    #[test]
+    fn long_runs_match_elapsed_modulo_period() {
+        let mut orbit = Orbit::circular(10.0, 0.5, 1.0);
+        orbit.eccentricity = 0.3;
+        //un millon de frames a 60 fps con el tiempo x10: unas 46 horas simuladas
+        let (steps, step) = (1_000_000, 10.0f32 / 60.0);
+        for _ in 0..steps {
+            orbit.advance(step);
+        }
+        let period = TAU / 0.5;
+        let mut reference = orbit.clone();
+        reference.elapsed = (steps as f64 * step as f64) % period;
+        assert!((orbit.position() - reference.position()).norm() < 1e-3);
+    }
+
+    #[test]
+    fn faster_near_periapsis() {
+        let mut orbit = Orbit::circular(10.0, 0.01, 0.0);
+        orbit.eccentricity = 0.5;
+        let periapsis = orbit.position();
+        orbit.advance(1.0);
+        let near = (orbit.position() - periapsis).norm();
+
+        orbit.mean_anomaly_at_epoch = PI;
+        orbit.elapsed = 0.0;
+        let apoapsis = orbit.position();
+        orbit.advance(1.0);
+        let far = (orbit.position() - apoapsis).norm();
+
+        assert!((periapsis.norm() - 5.0).abs() < 1e-4);
+        assert!((apoapsis.norm() - 15.0).abs() < 1e-3);
+        assert!(near > far);
+    }
+}
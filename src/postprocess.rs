@@ -0,0 +1,432 @@
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Deserializer};
+
+use crate::camera::{FAR, NEAR};
+use crate::color::{hex_to_linear, parse_hex};
+use crate::framebuffer::Framebuffer;
+
+//Cadena de efectos despues del sombreado. La niebla y la profundidad de campo
+//mezclan luz (van sobre el color HDR, antes del tone mapping); el resto
+//trabaja sobre la imagen final, en el orden de la lista. Cada efecto se
+//prende y apaga en vivo.
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Effect {
+    //oscurece las esquinas; `size` es la parte del centro que no se toca (0 a 1)
+    Vignette {
+        #[serde(default = "default_vignette_strength")]
+        strength: f32,
+        #[serde(default = "default_vignette_size")]
+        size: f32,
+    },
+    //rojo y azul se separan hacia los bordes, `strength` pixeles en la esquina
+    ChromaticAberration {
+        #[serde(default = "default_aberration_strength")]
+        strength: f32,
+    },
+    //ruido distinto en cada frame
+    FilmGrain {
+        #[serde(default = "default_grain_strength")]
+        strength: f32,
+    },
+    //una linea oscura cada `period` filas, como un televisor
+    Scanlines {
+        #[serde(default = "default_scanline_strength")]
+        strength: f32,
+        #[serde(default = "default_scanline_period")]
+        period: usize,
+    },
+    //`bits` por canal con dithering ordenado (5 = los 15 bits de la N64)
+    Dither {
+        #[serde(default = "default_dither_bits")]
+        bits: u32,
+    },
+    //desenfoca lo que esta lejos de `focus` (distancia a la camara); a
+    //`range` unidades del foco el desenfoque llega a `radius` pixeles
+    DepthOfField {
+        #[serde(default = "default_focus")]
+        focus: f32,
+        #[serde(default = "default_focus_range")]
+        range: f32,
+        #[serde(default = "default_blur_radius")]
+        radius: f32,
+    },
+    //lo que esta entre `start` y `end` de la camara se funde con `color`;
+    //el fondo (sin profundidad) no se toca
+    Fog {
+        #[serde(default = "default_fog_color", deserialize_with = "linear_color")]
+        color: Vec3,
+        #[serde(default = "default_fog_start")]
+        start: f32,
+        #[serde(default = "default_fog_end")]
+        end: f32,
+    },
+}
+
+//"#RRGGBB" de la escena -> luz lineal
+fn linear_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec3, D::Error> {
+    let color = String::deserialize(deserializer)?;
+    parse_hex(&color)
+        .map(hex_to_linear)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid color: {}", color)))
+}
+
+fn default_vignette_strength() -> f32 {
+    0.5
+}
+
+fn default_vignette_size() -> f32 {
+    0.4
+}
+
+fn default_aberration_strength() -> f32 {
+    3.0
+}
+
+fn default_grain_strength() -> f32 {
+    0.05
+}
+
+fn default_scanline_strength() -> f32 {
+    0.3
+}
+
+fn default_scanline_period() -> usize {
+    2
+}
+
+fn default_dither_bits() -> u32 {
+    5
+}
+
+fn default_focus() -> f32 {
+    10.0
+}
+
+fn default_focus_range() -> f32 {
+    20.0
+}
+
+fn default_blur_radius() -> f32 {
+    6.0
+}
+
+fn default_fog_color() -> Vec3 {
+    hex_to_linear(0x151515)
+}
+
+fn default_fog_start() -> f32 {
+    10.0
+}
+
+fn default_fog_end() -> f32 {
+    60.0
+}
+
+impl Effect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::Vignette { .. } => "vignette",
+            Effect::ChromaticAberration { .. } => "chromatic_aberration",
+            Effect::FilmGrain { .. } => "film_grain",
+            Effect::Scanlines { .. } => "scanlines",
+            Effect::Dither { .. } => "dither",
+            Effect::DepthOfField { .. } => "depth_of_field",
+            Effect::Fog { .. } => "fog",
+        }
+    }
+
+    //trabaja con luz y profundidad, antes del tone mapping
+    pub fn is_linear(&self) -> bool {
+        matches!(self, Effect::DepthOfField { .. } | Effect::Fog { .. })
+    }
+
+    //valores fuera de rango, para rechazar la escena al cargarla
+    pub fn validate(&self) -> Result<(), String> {
+        let unit = |value: f32| (0.0..=1.0).contains(&value);
+        let positive = |value: f32| value > 0.0 && value.is_finite();
+        let valid = match *self {
+            Effect::Vignette { strength, size } => unit(strength) && (0.0..1.0).contains(&size),
+            Effect::ChromaticAberration { strength } => strength >= 0.0 && strength.is_finite(),
+            Effect::FilmGrain { strength } => unit(strength),
+            Effect::Scanlines { strength, period } => unit(strength) && period >= 2,
+            Effect::Dither { bits } => (1..=8).contains(&bits),
+            Effect::DepthOfField { focus, range, radius } => {
+                positive(focus) && positive(range) && (0.0..=MAX_BLUR_RADIUS).contains(&radius)
+            }
+            Effect::Fog { start, end, .. } => start >= 0.0 && end > start && end.is_finite(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("invalid {} settings: {:?}", self.name(), self))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pass {
+    pub effect: Effect,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PostProcess {
+    pub passes: Vec<Pass>,
+}
+
+impl PostProcess {
+    //prende o apaga el efecto `index` de la lista, si existe
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(pass) = self.passes.get_mut(index) {
+            pass.enabled = !pass.enabled;
+        }
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Effect> {
+        self.passes.iter().filter(|pass| pass.enabled).map(|pass| &pass.effect)
+    }
+
+    //efectos sobre `framebuffer.color` (luz lineal), antes de `hdr::present`
+    pub fn apply_linear(&self, framebuffer: &mut Framebuffer) {
+        for effect in self.enabled() {
+            match *effect {
+                Effect::DepthOfField { focus, range, radius } => depth_of_field(framebuffer, focus, range, radius),
+                Effect::Fog { color, start, end } => fog(framebuffer, &color, start, end),
+                _ => {}
+            }
+        }
+    }
+
+    //efectos sobre `framebuffer.buffer` (la imagen final), despues de `hdr::present`
+    pub fn apply(&self, framebuffer: &mut Framebuffer, time: f32) {
+        let mut effects = self.enabled().filter(|effect| !effect.is_linear()).peekable();
+        if effects.peek().is_none() {
+            return;
+        }
+
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let mut image: Vec<Vec3> = framebuffer.buffer.iter().map(|&pixel| unpack(pixel)).collect();
+        for effect in effects {
+            match *effect {
+                Effect::Vignette { strength, size } => vignette(&mut image, width, height, strength, size),
+                Effect::ChromaticAberration { strength } => chromatic_aberration(&mut image, width, height, strength),
+                Effect::FilmGrain { strength } => film_grain(&mut image, width, strength, time),
+                Effect::Scanlines { strength, period } => scanlines(&mut image, width, strength, period),
+                Effect::Dither { bits } => dither(&mut image, width, bits),
+                Effect::DepthOfField { .. } | Effect::Fog { .. } => {}
+            }
+        }
+        for (pixel, color) in framebuffer.buffer.iter_mut().zip(&image) {
+            *pixel = pack(color);
+        }
+    }
+}
+
+fn unpack(pixel: u32) -> Vec3 {
+    let channel = |shift: u32| ((pixel >> shift) & 0xFF) as f32 / 255.0;
+    Vec3::new(channel(16), channel(8), channel(0))
+}
+
+fn pack(color: &Vec3) -> u32 {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+    (channel(color.x) << 16) | (channel(color.y) << 8) | channel(color.z)
+}
+
+//posicion del pixel respecto al centro: 0 en el centro, 1 en una esquina
+fn from_center(x: usize, y: usize, width: usize, height: usize) -> (f32, f32) {
+    let half = (width as f32 / 2.0, height as f32 / 2.0);
+    let diagonal = (half.0 * half.0 + half.1 * half.1).sqrt();
+    ((x as f32 + 0.5 - half.0) / diagonal, (y as f32 + 0.5 - half.1) / diagonal)
+}
+
+fn vignette(image: &mut [Vec3], width: usize, height: usize, strength: f32, size: f32) {
+    for (i, color) in image.iter_mut().enumerate() {
+        let (dx, dy) = from_center(i % width, i / width, width, height);
+        let t = (((dx * dx + dy * dy).sqrt() - size) / (1.0 - size)).clamp(0.0, 1.0);
+        //smoothstep: sin borde visible donde empieza
+        *color *= 1.0 - strength * t * t * (3.0 - 2.0 * t);
+    }
+}
+
+fn chromatic_aberration(image: &mut [Vec3], width: usize, height: usize, strength: f32) {
+    let source = image.to_vec();
+    let fetch = |x: f32, y: f32| {
+        let x = (x.round().max(0.0) as usize).min(width - 1);
+        let y = (y.round().max(0.0) as usize).min(height - 1);
+        source[y * width + x]
+    };
+    for (i, color) in image.iter_mut().enumerate() {
+        let (x, y) = (i % width, i / width);
+        //el desplazamiento crece con la distancia al centro
+        let (dx, dy) = from_center(x, y, width, height);
+        let (ox, oy) = (dx * strength, dy * strength);
+        color.x = fetch(x as f32 + ox, y as f32 + oy).x;
+        color.z = fetch(x as f32 - ox, y as f32 - oy).z;
+    }
+}
+
+//entero pseudoaleatorio por pixel y frame, sin estado (mismo resultado con
+//cualquier cantidad de hilos o en modo headless)
+fn hash(mut value: u32) -> u32 {
+    value ^= value >> 16;
+    value = value.wrapping_mul(0x7FEB_352D);
+    value ^= value >> 15;
+    value = value.wrapping_mul(0x846C_A68B);
+    value ^ (value >> 16)
+}
+
+fn film_grain(image: &mut [Vec3], width: usize, strength: f32, time: f32) {
+    let frame = (time * 60.0) as i64 as u32;
+    for (i, color) in image.iter_mut().enumerate() {
+        let (x, y) = ((i % width) as u32, (i / width) as u32);
+        let noise = hash(x ^ hash(y ^ hash(frame))) as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        //mismo ruido en los tres canales: grano de luminancia
+        color.add_scalar_mut(noise * strength);
+    }
+}
+
+fn scanlines(image: &mut [Vec3], width: usize, strength: f32, period: usize) {
+    for (y, row) in image.chunks_exact_mut(width).enumerate() {
+        if y % period == period - 1 {
+            for color in row {
+                *color *= 1.0 - strength;
+            }
+        }
+    }
+}
+
+//matriz de Bayer 4x4, umbrales de 0 a 15
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+fn dither(image: &mut [Vec3], width: usize, bits: u32) {
+    let levels = ((1u32 << bits) - 1) as f32;
+    for (i, color) in image.iter_mut().enumerate() {
+        let threshold = (BAYER[(i / width) % 4][(i % width) % 4] as f32 + 0.5) / 16.0;
+        //cada canal baja a `levels` escalones; el umbral decide hacia cual redondea
+        *color = color.map(|value| ((value.clamp(0.0, 1.0) * levels + threshold) as u32 as f32).min(levels) / levels);
+    }
+}
+
+//mas alla de esto el desenfoque necesitaria mas muestras para no verse a bloques
+const MAX_BLUR_RADIUS: f32 = 16.0;
+//muestras del disco de desenfoque, en espiral de angulo aureo
+const BLUR_TAPS: usize = 16;
+
+//profundidad del zbuffer (z de -1 a 1 tras la proyeccion) -> distancia a la camara
+fn linear_depth(depth: f32) -> f32 {
+    if !depth.is_finite() {
+        return FAR;
+    }
+    2.0 * NEAR * FAR / (FAR + NEAR - depth * (FAR - NEAR))
+}
+
+//profundidad de la muestra mas cercana de cada pixel
+fn pixel_depths(framebuffer: &Framebuffer) -> impl Iterator<Item = f32> + '_ {
+    let samples = framebuffer.antialiasing().samples();
+    framebuffer
+        .zbuffer
+        .chunks_exact(samples)
+        .map(|depths| depths.iter().copied().fold(f32::INFINITY, f32::min))
+}
+
+fn fog(framebuffer: &mut Framebuffer, fog_color: &Vec3, start: f32, end: f32) {
+    let amounts: Vec<f32> = pixel_depths(framebuffer)
+        .map(|depth| if depth.is_finite() { ((linear_depth(depth) - start) / (end - start)).clamp(0.0, 1.0) } else { 0.0 })
+        .collect();
+    for (color, amount) in framebuffer.color.iter_mut().zip(amounts) {
+        *color = color.lerp(fog_color, amount);
+    }
+}
+
+fn depth_of_field(framebuffer: &mut Framebuffer, focus: f32, range: f32, radius: f32) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    //radio de desenfoque de cada pixel
+    let blur: Vec<f32> = pixel_depths(framebuffer)
+        .map(|depth| ((linear_depth(depth) - focus).abs() / range).min(1.0) * radius)
+        .collect();
+
+    let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+    let disk: Vec<(f32, f32)> = (0..BLUR_TAPS)
+        .map(|i| {
+            let distance = ((i as f32 + 0.5) / BLUR_TAPS as f32).sqrt();
+            let angle = i as f32 * golden_angle;
+            (angle.cos() * distance, angle.sin() * distance)
+        })
+        .collect();
+
+    let source = framebuffer.color.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let own = blur[i];
+            if own < 0.5 {
+                continue;
+            }
+            let mut sum = source[i];
+            let mut weight = 1.0;
+            for &(dx, dy) in &disk {
+                let sx = (x as f32 + dx * own).round().clamp(0.0, (width - 1) as f32) as usize;
+                let sy = (y as f32 + dy * own).round().clamp(0.0, (height - 1) as f32) as usize;
+                let j = sy * width + sx;
+                //un vecino enfocado no se derrama sobre lo desenfocado de atras
+                let distance = (dx * dx + dy * dy).sqrt() * own;
+                let w = (blur[j] - distance + 1.0).clamp(0.0, 1.0);
+                sum += source[j] * w;
+                weight += w;
+            }
+            framebuffer.color[i] = sum / weight;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dither_keeps_the_average() {
+        let width = 4;
+        let mut image = vec![Vec3::repeat(0.3); 16];
+        dither(&mut image, width, 2);
+        //solo quedan los escalones de 2 bits
+        for color in &image {
+            assert!([0.0, 1.0 / 3.0].contains(&color.x), "{}", color.x);
+        }
+        //el promedio del patron 4x4 se acerca al gris original
+        let average = image.iter().map(|color| color.x).sum::<f32>() / 16.0;
+        assert!((average - 0.3).abs() < 1.0 / 48.0, "{}", average);
+    }
+
+    #[test]
+    fn vignette_darkens_only_the_corners() {
+        let (width, height) = (40, 30);
+        let mut image = vec![Vec3::repeat(1.0); width * height];
+        vignette(&mut image, width, height, 0.5, 0.4);
+        assert_eq!(image[15 * width + 20], Vec3::repeat(1.0));
+        assert!((image[0].x - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn depth_of_field_keeps_the_focus_sharp() {
+        let mut framebuffer = Framebuffer::new(16, 16);
+        for (i, color) in framebuffer.color.iter_mut().enumerate() {
+            *color = Vec3::repeat(((i % 16) % 2) as f32);
+        }
+        //mitad izquierda a 10 unidades (en foco), la derecha al fondo
+        let depth_at = |distance: f32| (FAR + NEAR - 2.0 * NEAR * FAR / distance) / (FAR - NEAR);
+        for (i, depth) in framebuffer.zbuffer.iter_mut().enumerate() {
+            *depth = if i % 16 < 8 { depth_at(10.0) } else { f32::INFINITY };
+        }
+        assert!((linear_depth(depth_at(10.0)) - 10.0).abs() < 1e-2);
+
+        let original = framebuffer.color.clone();
+        depth_of_field(&mut framebuffer, 10.0, 20.0, 4.0);
+        let row = 8 * 16;
+        assert_eq!(framebuffer.color[row + 2], original[row + 2]);
+        //las rayas del fondo se funden en gris
+        let blurred = framebuffer.color[row + 13].x;
+        assert!(blurred > 0.2 && blurred < 0.8, "{}", blurred);
+    }
+}
@@ -0,0 +1,58 @@
+use serde::Deserialize;
+
+use crate::lighting::Shading;
+use crate::texture::Filter;
+
+//Estilo del render. `n64` imita a la consola: dibuja a 320x240 y lo agranda a
+//la ventana sin suavizar, filtra las texturas con tres texels, calcula la luz
+//en los vertices (Gouraud) y baja el color a 15 bits con dithering ordenado.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Modern,
+    N64,
+}
+
+pub const NAMES: &str = "modern, n64";
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "modern" => Some(Preset::Modern),
+            "n64" => Some(Preset::N64),
+            _ => None,
+        }
+    }
+
+    //resolucion interna; `None` dibuja directo a la de salida
+    pub fn resolution(self) -> Option<(usize, usize)> {
+        match self {
+            Preset::Modern => None,
+            Preset::N64 => Some((320, 240)),
+        }
+    }
+
+    pub fn shading(self) -> Shading {
+        match self {
+            Preset::Modern => Shading::Pixel,
+            Preset::N64 => Shading::Gouraud,
+        }
+    }
+
+    //filtro que reemplaza al de todas las texturas de la escena
+    pub fn texture_filter(self) -> Option<Filter> {
+        match self {
+            Preset::Modern => None,
+            Preset::N64 => Some(Filter::ThreePoint),
+        }
+    }
+
+    //bits por canal de la imagen final (5 = 15 bits, el modo de color de la N64)
+    pub fn color_bits(self) -> Option<u32> {
+        match self {
+            Preset::Modern => None,
+            Preset::N64 => Some(5),
+        }
+    }
+}
@@ -0,0 +1,1408 @@
+use nalgebra_glm::{mat4_to_mat3, Vec3};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::bvh::{Aabb, Bvh, Ray};
+use crate::camera::Camera;
+use crate::camera_rig::{CameraMode, CameraRig, RigSettings};
+use crate::camera_track::{CameraTrack, TrackError};
+use crate::clock::FixedStep;
+use crate::collision::{move_hull, Collider, CollisionEvent, CollisionSettings, CollisionShape, Hull};
+use crate::color::{hex_to_linear, parse_hex};
+use crate::controls::Controls;
+use crate::culling::{bounding_sphere, FaceCulling};
+use crate::hdr::{Bloom, HdrSettings, ToneMapping};
+use crate::lighting::{Light, Lighting};
+use crate::nbody::{Diagnostics, Integrator, NBody, Particle};
+use crate::obj::Obj;
+use crate::orbit::Orbit;
+use crate::postprocess::{Effect, Pass, PostProcess};
+use crate::preset::Preset;
+use crate::scene_graph::{Model, Node, NodeId, Renderable, SceneGraph, Transform};
+use crate::shaders::{ShaderError, ShaderParams, ShaderRegistry};
+use crate::spaceship::{FlightModel, ShipInput, Spaceship};
+use crate::starfield::{parse_catalogue, Starfield, StarfieldSettings};
+use crate::texture::{Filter, Sampler, Texture, TextureSet, Wrap};
+
+pub const DEFAULT_SCENE: &str = "assets/scenes/solar_system.toml";
+
+//Descripcion del archivo de escena (TOML)
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub background: BackgroundDescription,
+    pub camera: CameraDescription,
+    pub ship: ShipDescription,
+    #[serde(default)]
+    pub physics: PhysicsDescription,
+    #[serde(default)]
+    pub render: RenderDescription,
+    #[serde(default)]
+    pub lighting: LightingDescription,
+    #[serde(default)]
+    pub hdr: HdrDescription,
+    #[serde(default)]
+    pub post: Vec<PostEffectDescription>,
+    #[serde(default)]
+    pub bodies: Vec<BodyDescription>,
+    //accion -> tecla, ver `Controls`
+    #[serde(default)]
+    pub controls: HashMap<String, String>,
+}
+
+impl SceneDescription {
+    pub fn load(path: &str) -> Result<Self, SceneError> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| SceneError::Io(path.to_string(), err))?;
+        toml::from_str(&source).map_err(SceneError::Parse)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PhysicsMode {
+    //orbitas keplerianas fijas
+    Kepler,
+    //gravedad entre los cuerpos con `mass`
+    Nbody,
+}
+
+impl PhysicsMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "kepler" => Some(PhysicsMode::Kepler),
+            "nbody" => Some(PhysicsMode::Nbody),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhysicsDescription {
+    #[serde(default = "default_physics_mode")]
+    pub mode: PhysicsMode,
+    #[serde(default = "default_integrator")]
+    pub integrator: Integrator,
+    #[serde(default = "default_gravitational_constant")]
+    pub gravitational_constant: f64,
+    //segundos de simulacion por paso
+    #[serde(default = "default_timestep")]
+    pub timestep: f64,
+    #[serde(default)]
+    pub softening: f64,
+    #[serde(default = "default_true")]
+    pub ship_gravity: bool,
+}
+
+impl Default for PhysicsDescription {
+    fn default() -> Self {
+        PhysicsDescription {
+            mode: default_physics_mode(),
+            integrator: default_integrator(),
+            gravitational_constant: default_gravitational_constant(),
+            timestep: default_timestep(),
+            softening: 0.0,
+            ship_gravity: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightingDescription {
+    #[serde(default = "default_ambient")]
+    pub ambient: f32,
+    #[serde(default = "default_light_color")]
+    pub ambient_color: String,
+    #[serde(default)]
+    pub directional: Vec<DirectionalLightDescription>,
+}
+
+impl Default for LightingDescription {
+    fn default() -> Self {
+        LightingDescription {
+            ambient: default_ambient(),
+            ambient_color: default_light_color(),
+            directional: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectionalLightDescription {
+    //hacia donde viaja la luz
+    pub direction: [f32; 3],
+    #[serde(default = "default_light_color")]
+    pub color: String,
+    #[serde(default = "default_light_intensity")]
+    pub intensity: f32,
+}
+
+//Luz puntual pegada a un cuerpo (el sol)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PointLightDescription {
+    #[serde(default = "default_light_color")]
+    pub color: String,
+    #[serde(default = "default_light_intensity")]
+    pub intensity: f32,
+    #[serde(default)]
+    pub attenuation: f32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderDescription {
+    //reordena los indices de cada malla al cargarla para aprovechar la cache de vertices
+    #[serde(default = "default_true")]
+    pub optimize_meshes: bool,
+    //"modern" o "n64" (baja resolucion, Gouraud, color de 15 bits)
+    #[serde(default)]
+    pub preset: Preset,
+}
+
+impl Default for RenderDescription {
+    fn default() -> Self {
+        RenderDescription { optimize_meshes: true, preset: Preset::default() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HdrDescription {
+    #[serde(default = "default_exposure")]
+    pub exposure: f32,
+    #[serde(default)]
+    pub tone_mapping: ToneMapping,
+    #[serde(default = "default_true")]
+    pub bloom: bool,
+    //brillo (en luz lineal) desde el que una superficie se difumina
+    #[serde(default = "default_bloom_threshold")]
+    pub bloom_threshold: f32,
+    #[serde(default = "default_bloom_intensity")]
+    pub bloom_intensity: f32,
+    #[serde(default = "default_bloom_radius")]
+    pub bloom_radius: usize,
+}
+
+impl Default for HdrDescription {
+    fn default() -> Self {
+        HdrDescription {
+            exposure: default_exposure(),
+            tone_mapping: ToneMapping::default(),
+            bloom: true,
+            bloom_threshold: default_bloom_threshold(),
+            bloom_intensity: default_bloom_intensity(),
+            bloom_radius: default_bloom_radius(),
+        }
+    }
+}
+
+//Un efecto de post-proceso: `effect = "vignette"` y sus parametros
+#[derive(Debug, Deserialize)]
+pub struct PostEffectDescription {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub effect: Effect,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackgroundDescription {
+    #[serde(default = "default_background_color")]
+    pub color: String,
+    //estrellas generadas; con la misma `seed` el cielo sale igual
+    #[serde(default)]
+    pub stars: usize,
+    #[serde(default = "default_star_seed")]
+    pub seed: u64,
+    #[serde(default = "default_magnitude_limit")]
+    pub magnitude_limit: f32,
+    #[serde(default = "default_star_brightness")]
+    pub star_brightness: f32,
+    #[serde(default)]
+    pub twinkle: f32,
+    #[serde(default)]
+    pub milky_way: f32,
+    #[serde(default)]
+    pub nebula: f32,
+    //CSV de estrellas reales (ra, dec, mag, bv)
+    pub catalogue: Option<String>,
+}
+
+impl Default for BackgroundDescription {
+    fn default() -> Self {
+        BackgroundDescription {
+            color: default_background_color(),
+            stars: 0,
+            seed: default_star_seed(),
+            magnitude_limit: default_magnitude_limit(),
+            star_brightness: default_star_brightness(),
+            twinkle: 0.0,
+            milky_way: 0.0,
+            nebula: 0.0,
+            catalogue: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub eye: [f32; 3],
+    //si falta, la camara mira a la nave
+    pub center: Option<[f32; 3]>,
+    #[serde(default = "default_up")]
+    pub up: [f32; 3],
+    #[serde(default)]
+    pub mode: CameraMode,
+    //cuerpo de las camaras orbit y tracking (por defecto el primero)
+    pub target: Option<String>,
+    //recorrido grabado para el modo track (ver `CameraTrack`)
+    pub track: Option<String>,
+    //resortes, apertura y mezcla entre modos
+    #[serde(default)]
+    pub rig: RigSettings,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShipDescription {
+    pub model: String,
+    pub shader: String,
+    #[serde(default)]
+    pub position: [f32; 3],
+    //hacia donde apunta la nariz al empezar (por defecto -z)
+    pub forward: Option<[f32; 3]>,
+    #[serde(default)]
+    pub flight: FlightModel,
+    //respuesta a los choques con los cuerpos
+    #[serde(default)]
+    pub collision: CollisionSettings,
+    #[serde(default)]
+    pub params: ShaderParams,
+    #[serde(default)]
+    pub double_sided: bool,
+    #[serde(default)]
+    pub textures: TexturesDescription,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BodyDescription {
+    pub name: String,
+    pub model: String,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    pub shader: String,
+    #[serde(default)]
+    pub params: ShaderParams,
+    //posicion relativa al padre (cuerpos sin orbita)
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    pub parent: Option<String>,
+    pub orbit: Option<OrbitDescription>,
+    //solo para el modo nbody
+    pub mass: Option<f64>,
+    //dibuja tambien las caras traseras (mallas planas o abiertas)
+    #[serde(default)]
+    pub double_sided: bool,
+    //el cuerpo emite luz (se dibuja igual, su shader decide si brilla)
+    pub light: Option<PointLightDescription>,
+    //forma con la que choca la nave ("sphere" o "none")
+    #[serde(default)]
+    pub collision: CollisionShape,
+    #[serde(default)]
+    pub textures: TexturesDescription,
+}
+
+//Imagenes (PNG o JPEG) que usan los shaders; todas son opcionales
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TexturesDescription {
+    pub albedo: Option<String>,
+    pub normal: Option<String>,
+    pub specular: Option<String>,
+    pub night: Option<String>,
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub wrap: Wrap,
+    #[serde(default = "default_true")]
+    pub mipmaps: bool,
+}
+
+//Elementos orbitales, angulos en radianes. `radius` y `phase` siguen
+//valiendo para orbitas circulares.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrbitDescription {
+    #[serde(alias = "radius")]
+    pub semi_major_axis: f32,
+    #[serde(default)]
+    pub eccentricity: f32,
+    #[serde(default)]
+    pub inclination: f32,
+    #[serde(default)]
+    pub longitude_of_ascending_node: f32,
+    #[serde(default)]
+    pub argument_of_periapsis: f32,
+    #[serde(default, alias = "phase")]
+    pub mean_anomaly: f32,
+    //anomalia media que avanza por segundo
+    pub speed: f32,
+}
+
+fn default_ambient() -> f32 {
+    0.05
+}
+
+fn default_light_color() -> String {
+    "#FFFFFF".to_string()
+}
+
+fn default_light_intensity() -> f32 {
+    1.0
+}
+
+fn default_exposure() -> f32 {
+    HdrSettings::default().exposure
+}
+
+fn default_bloom_threshold() -> f32 {
+    Bloom::default().threshold
+}
+
+fn default_bloom_intensity() -> f32 {
+    Bloom::default().intensity
+}
+
+fn default_bloom_radius() -> usize {
+    Bloom::default().radius
+}
+
+fn default_background_color() -> String {
+    "#151515".to_string()
+}
+
+fn default_star_seed() -> u64 {
+    StarfieldSettings::default().seed
+}
+
+fn default_magnitude_limit() -> f32 {
+    StarfieldSettings::default().magnitude_limit
+}
+
+fn default_star_brightness() -> f32 {
+    StarfieldSettings::default().brightness
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_physics_mode() -> PhysicsMode {
+    PhysicsMode::Kepler
+}
+
+fn default_integrator() -> Integrator {
+    Integrator::Leapfrog
+}
+
+fn default_gravitational_constant() -> f64 {
+    1.0
+}
+
+fn default_timestep() -> f64 {
+    1.0 / 240.0
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(String, std::io::Error),
+    Parse(toml::de::Error),
+    Model(String, tobj::LoadError),
+    Texture(String, image::ImageError),
+    Shader(String, ShaderError),
+    Track(String, TrackError),
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, err) => write!(f, "cannot read {}: {}", path, err),
+            SceneError::Parse(err) => write!(f, "invalid scene file: {}", err),
+            SceneError::Model(path, err) => write!(f, "cannot load model {}: {}", path, err),
+            SceneError::Texture(path, err) => write!(f, "cannot load texture {}: {}", path, err),
+            SceneError::Shader(owner, err) => write!(f, "{}: {}", owner, err),
+            SceneError::Track(path, err) => write!(f, "cannot load camera track {}: {}", path, err),
+            SceneError::Invalid(msg) => write!(f, "invalid scene: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+//Escena ya construida
+
+pub const SHIP_NODE: &str = "ship";
+
+pub struct Scene {
+    pub graph: SceneGraph,
+    pub ship_node: NodeId,
+    pub spaceship: Spaceship,
+    pub gravity: Option<Gravity>,
+    //la que se dibuja; la escribe `camera_rig` salvo que se fije a mano
+    pub camera: Camera,
+    pub camera_rig: CameraRig,
+    //teclas de la nave
+    pub controls: Controls,
+    pub collision: CollisionSettings,
+    //cuerpos contra los que choca la nave
+    colliders: Vec<NodeId>,
+    //cuerpos tocados en el frame anterior: un contacto que sigue no se repite
+    contacts: Vec<NodeId>,
+    //cuerpos tocados en este frame; pasan a `contacts` al final de `update`
+    touching: Vec<NodeId>,
+    //choques nuevos desde el ultimo `take_collisions`
+    collisions: Vec<CollisionEvent>,
+    //esferas de mundo de los cuerpos (sin la nave) y su BVH, que se reajusta
+    //en cada `update` porque los planetas se mueven
+    bodies: Vec<BodySphere>,
+    body_tree: Bvh,
+    //fondo, estrellas y Via Lactea; se dibuja cada frame segun la camara
+    pub starfield: Starfield,
+    //exposicion, tone mapping y bloom del frame; la exposicion cambia con el teclado
+    pub hdr: HdrSettings,
+    //efectos despues del sombreado, se prenden y apagan con F1..F9
+    pub post: PostProcess,
+    //resolucion interna, sombreado y colores (ver `Preset`)
+    pub preset: Preset,
+    //ambiente y direccionales, fijas
+    lights: Vec<Light>,
+    //luces puntuales que siguen a su nodo
+    point_lights: Vec<(NodeId, Light)>,
+}
+
+impl Scene {
+    pub fn build(description: &SceneDescription, shaders: &ShaderRegistry) -> Result<Self, SceneError> {
+        let mut models = ModelCache {
+            optimize: description.render.optimize_meshes,
+            ..ModelCache::default()
+        };
+        let preset = description.render.preset;
+        let mut textures = TextureCache {
+            filter: preset.texture_filter(),
+            ..TextureCache::default()
+        };
+        let mut graph = SceneGraph::default();
+
+        //la nave es un nodo mas, asi se le pueden colgar piezas
+        let ship = &description.ship;
+        let mut spaceship = Spaceship::new(to_vec3(ship.position));
+        if let Some(forward) = ship.forward {
+            spaceship.look_towards(&to_vec3(forward));
+        }
+        spaceship.flight = build_flight(&ship.flight, description.physics.mode)?;
+        let collision = build_collision(&ship.collision)?;
+        let mut ship_node = Node::new(SHIP_NODE, None, Transform::new(spaceship.position, spaceship.euler_angles()));
+        ship_node.renderable = Some(Renderable {
+            model: models.load(&ship.model)?,
+            shader: shaders
+                .program(&ship.shader)
+                .map_err(|err| SceneError::Shader(SHIP_NODE.to_string(), err))?,
+            params: ship.params.clone(),
+            scale: 1.0,
+            culling: face_culling(ship.double_sided),
+            textures: textures.load(&ship.textures)?,
+        });
+        let ship_node = graph.add(ship_node);
+        let mut point_lights = Vec::new();
+        let mut colliders = Vec::new();
+
+        for body in &description.bodies {
+            if body.name.is_empty() {
+                return Err(SceneError::Invalid("body without name".to_string()));
+            }
+            if graph.find(&body.name).is_some() {
+                return Err(SceneError::Invalid(format!("duplicate body name: {}", body.name)));
+            }
+            if body.scale <= 0.0 || !body.scale.is_finite() {
+                return Err(SceneError::Invalid(format!("{}: scale must be positive", body.name)));
+            }
+
+            let parent = match &body.parent {
+                Some(parent) => Some(graph.find(parent).ok_or_else(|| {
+                    SceneError::Invalid(format!("{}: parent {} must be declared before it", body.name, parent))
+                })?),
+                None => None,
+            };
+
+            let mut node = Node::new(
+                &body.name,
+                parent,
+                Transform::new(to_vec3(body.position), to_vec3(body.rotation)),
+            );
+
+            if body.mass.is_some_and(|mass| mass < 0.0 || !mass.is_finite()) {
+                return Err(SceneError::Invalid(format!("{}: mass must be non-negative", body.name)));
+            }
+
+            if let Some(orbit) = &body.orbit {
+                if parent.is_none() {
+                    return Err(SceneError::Invalid(format!("{}: orbit requires a parent", body.name)));
+                }
+                node.orbit = Some(build_orbit(&body.name, orbit)?);
+            }
+
+            node.renderable = Some(Renderable {
+                model: models.load(&body.model)?,
+                shader: shaders
+                    .program(&body.shader)
+                    .map_err(|err| SceneError::Shader(body.name.clone(), err))?,
+                params: body.params.clone(),
+                scale: body.scale,
+                culling: face_culling(body.double_sided),
+                textures: textures.load(&body.textures)?,
+            });
+
+            let id = graph.add(node);
+            if body.collision == CollisionShape::Sphere && !on_ship(&graph, ship_node, id) {
+                colliders.push(id);
+            }
+            if let Some(light) = &body.light {
+                point_lights.push((id, build_point_light(&body.name, light)?));
+            }
+        }
+
+        let camera_description = &description.camera;
+        let camera = Camera::new(
+            to_vec3(camera_description.eye),
+            camera_description.center.map(to_vec3).unwrap_or(spaceship.position),
+            to_vec3(camera_description.up),
+        );
+        let camera_rig = build_camera_rig(camera_description, &graph, ship_node, camera)?;
+
+        graph.update_world_matrices();
+
+        let gravity = match description.physics.mode {
+            PhysicsMode::Kepler => None,
+            PhysicsMode::Nbody => Some(Gravity::build(description, &mut graph, ship_node, &spaceship)?),
+        };
+
+        let mut scene = Scene {
+            graph,
+            ship_node,
+            spaceship,
+            gravity,
+            camera,
+            camera_rig,
+            controls: Controls::from_names(&description.controls).map_err(SceneError::Invalid)?,
+            collision,
+            colliders,
+            contacts: Vec::new(),
+            touching: Vec::new(),
+            collisions: Vec::new(),
+            bodies: Vec::new(),
+            body_tree: Bvh::build(&[]),
+            starfield: build_starfield(&description.background)?,
+            hdr: build_hdr(&description.hdr)?,
+            post: build_post(&description.post, preset)?,
+            preset,
+            lights: build_lights(&description.lighting)?,
+            point_lights,
+        };
+        scene.bodies = scene
+            .graph
+            .nodes()
+            .iter()
+            .enumerate()
+            .filter(|(id, node)| node.renderable.is_some() && !on_ship(&scene.graph, ship_node, *id))
+            .map(|(id, _)| BodySphere { node: id, center: Vec3::zeros(), radius: 0.0, motion: Vec3::zeros(), velocity: Vec3::zeros() })
+            .collect();
+        scene.refresh_bodies();
+        Ok(scene)
+    }
+
+    //avanza las orbitas (o la gravedad) `seconds` de tiempo simulado, que puede
+    //ser 0 (pausa) o negativo, y recalcula el grafo con la nave en su lugar
+    pub fn update(&mut self, seconds: f32) {
+        let before: Vec<Vec3> = self.bodies.iter().map(|body| body.center).collect();
+        self.graph.advance_orbits(seconds);
+        let start = self.spaceship.position;
+        if let Some(gravity) = self.gravity.as_mut() {
+            gravity.advance(seconds, &mut self.graph, &mut self.spaceship);
+        }
+        //la gravedad pudo mover la nave y los cuerpos se movieron: se barre
+        //ese tramo con cada cuerpo en su marco, asi un planeta que alcanza a
+        //la nave quieta tambien choca
+        let motion = self.spaceship.position - start;
+        self.spaceship.position = start;
+        self.graph.update_world_matrices();
+        self.refresh_bodies();
+        for (body, before) in self.bodies.iter_mut().zip(before) {
+            body.motion = body.center - before;
+            body.velocity = match self.gravity.as_ref().and_then(|gravity| gravity.body_velocity(body.node)) {
+                Some(velocity) => velocity,
+                None if seconds != 0.0 => body.motion / seconds,
+                None => Vec3::zeros(),
+            };
+        }
+        self.sweep_ship(motion, true);
+        self.contacts = std::mem::take(&mut self.touching);
+
+        self.graph.node_mut(self.ship_node).local =
+            Transform::new(self.spaceship.position, self.spaceship.euler_angles());
+        self.graph.update_world_matrices();
+        self.refresh_bodies();
+    }
+
+    fn refresh_bodies(&mut self) {
+        for body in self.bodies.iter_mut() {
+            let node = self.graph.node(body.node);
+            let (Some(renderable), Some(matrix)) = (&node.renderable, node.mesh_matrix()) else { continue };
+            (body.center, body.radius) = bounding_sphere(&matrix, &renderable.model.center, renderable.model.radius);
+        }
+        let bounds: Vec<Aabb> = self.bodies.iter().map(|body| Aabb::around_sphere(&body.center, body.radius)).collect();
+        self.body_tree.update(&bounds);
+    }
+
+    //primer cuerpo que toca el rayo (contra sus triangulos) y a que distancia
+    pub fn ray_cast(&self, ray: &Ray, max_distance: f32) -> Option<(NodeId, f32)> {
+        let hit = |i: usize| {
+            let node = self.graph.node(self.bodies[i].node);
+            let renderable = node.renderable.as_ref()?;
+            let inverse = node.mesh_matrix()?.try_inverse()?;
+            //la escala es uniforme: la distancia local es la de mundo por la escala inversa
+            let direction = (inverse * ray.direction.push(0.0)).xyz();
+            let scale = direction.norm();
+            let local = Ray::new((inverse * ray.origin.push(1.0)).xyz(), direction);
+            renderable.model.bvh.ray_cast(&local, max_distance * scale).map(|(_, t)| t / scale)
+        };
+        self.body_tree.ray_cast(ray, max_distance, hit).map(|(i, t)| (self.bodies[i].node, t))
+    }
+
+    //cuerpos cuya esfera toca la caja
+    pub fn bodies_overlapping(&self, bounds: &Aabb) -> Vec<BodySphere> {
+        let mut found = Vec::new();
+        self.body_tree.query(
+            |node| node.overlaps(bounds),
+            |i| {
+                if bounds.overlaps_sphere(&self.bodies[i].center, self.bodies[i].radius) {
+                    found.push(self.bodies[i]);
+                }
+            },
+        );
+        found
+    }
+
+    //cuerpo mas cercano a `point` y distancia a su superficie (0 si esta adentro)
+    pub fn nearest_body(&self, point: &Vec3) -> Option<(NodeId, f32)> {
+        let distance = |i: usize| ((self.bodies[i].center - point).norm() - self.bodies[i].radius).max(0.0);
+        self.body_tree.nearest(point, distance).map(|(i, d)| (self.bodies[i].node, d))
+    }
+
+    //luces en mundo para este frame: las puntuales van donde esta su cuerpo
+    pub fn lighting(&self) -> Lighting {
+        let mut lights = self.lights.clone();
+        for &(node, light) in &self.point_lights {
+            if let Light::Point { color, intensity, attenuation, .. } = light {
+                let position = self.graph.node(node).world_position();
+                lights.push(Light::Point { position, color, intensity, attenuation });
+            }
+        }
+        Lighting::new(lights)
+    }
+
+    //controles de la nave por `seconds` de tiempo real. Si la gravedad la
+    //simula (`ship_gravity`), la mueve `update`; si no, se mueve aca
+    pub fn fly_ship(&mut self, input: &ShipInput, seconds: f32) {
+        self.spaceship.steer(input, seconds);
+        if !self.gravity.as_ref().is_some_and(Gravity::moves_ship) {
+            self.move_ship(seconds);
+        }
+    }
+
+    //mueve la nave con su velocidad `seconds`, chocando con los cuerpos
+    fn move_ship(&mut self, seconds: f32) {
+        let motion = self.spaceship.next_position(seconds) - self.spaceship.position;
+        self.sweep_ship(motion, false);
+    }
+
+    //choques nuevos desde la ultima llamada, para el juego
+    pub fn take_collisions(&mut self) -> Vec<CollisionEvent> {
+        std::mem::take(&mut self.collisions)
+    }
+
+    //`bodies_moved`: los cuerpos hicieron su `motion` durante este movimiento
+    fn sweep_ship(&mut self, motion: Vec3, bodies_moved: bool) {
+        //el casco con la orientacion de ahora, centrado como se dibuja la nave
+        let model = &self.graph.node(self.ship_node).renderable.as_ref().expect("ship is renderable").model;
+        let matrix = Transform::new(self.spaceship.position, self.spaceship.euler_angles()).matrix()
+            * nalgebra_glm::translation(&-model.center);
+        let hull = Hull::new(model, matrix);
+
+        //solo los cuerpos que toca la caja de todo el recorrido, agrandada por
+        //lo que se movieron los cuerpos
+        let reach = if bodies_moved { self.bodies.iter().map(|body| body.motion.norm()).fold(0.0, f32::max) } else { 0.0 };
+        let start = Aabb::around_sphere(&self.spaceship.position, model.radius + reach);
+        let swept = start.union(&Aabb { min: start.min + motion, max: start.max + motion });
+        let colliders: Vec<Collider> = self
+            .bodies_overlapping(&swept)
+            .into_iter()
+            .filter(|body| self.colliders.contains(&body.node))
+            .map(|body| {
+                let motion = if bodies_moved { body.motion } else { Vec3::zeros() };
+                Collider { node: body.node, center: body.center, radius: body.radius, motion, velocity: body.velocity }
+            })
+            .collect();
+        let spaceship = &mut self.spaceship;
+        let events = move_hull(&hull, &mut spaceship.position, &mut spaceship.velocity, motion, &colliders, &self.collision);
+
+        for event in events {
+            if !self.contacts.contains(&event.node) && !self.touching.contains(&event.node) {
+                self.collisions.push(event);
+            }
+            if !self.touching.contains(&event.node) {
+                self.touching.push(event.node);
+            }
+        }
+    }
+
+    //mueve la camara segun su modo; `input` solo se usa en orbit y free
+    pub fn update_camera(&mut self, input: &ShipInput, seconds: f32) {
+        let target = self.camera_rig.target().map(|id| {
+            let node = self.graph.node(id);
+            match (&node.renderable, node.mesh_matrix()) {
+                (Some(renderable), Some(matrix)) => {
+                    bounding_sphere(&matrix, &renderable.model.center, renderable.model.radius)
+                }
+                _ => (node.world_position(), 1.0),
+            }
+        });
+        self.camera = self.camera_rig.update(&self.spaceship, target, input, seconds);
+    }
+}
+
+//cuerpo de la escena con su esfera envolvente en mundo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodySphere {
+    pub node: NodeId,
+    pub center: Vec3,
+    pub radius: f32,
+    //cuanto se movio en el ultimo `update` y a que velocidad
+    pub motion: Vec3,
+    pub velocity: Vec3,
+}
+
+struct SimulatedBody {
+    node: NodeId,
+    particle: usize,
+}
+
+//Modo nbody: los cuerpos con masa se mueven por gravedad y la nave, si
+//`ship_gravity` esta activo, es una particula sin masa que la siente
+pub struct Gravity {
+    pub system: NBody,
+    steps: FixedStep,
+    bodies: Vec<SimulatedBody>,
+    ship: Option<usize>,
+    initial: Diagnostics,
+}
+
+impl Gravity {
+    fn build(
+        description: &SceneDescription,
+        graph: &mut SceneGraph,
+        ship_node: NodeId,
+        spaceship: &Spaceship,
+    ) -> Result<Self, SceneError> {
+        let physics = &description.physics;
+        if !(physics.timestep > 0.0 && physics.timestep.is_finite()) {
+            return Err(SceneError::Invalid("physics timestep must be positive".to_string()));
+        }
+        let mut system = NBody::new(physics.gravitational_constant, physics.softening, physics.integrator);
+
+        //velocidad inicial de cada nodo: la de su padre mas la de su orbita
+        let mut masses = vec![0.0; graph.nodes().len()];
+        let mut velocities = vec![Vec3::zeros(); graph.nodes().len()];
+        for body in &description.bodies {
+            let id = graph.find(&body.name).expect("body was added to the graph");
+            masses[id] = body.mass.unwrap_or(0.0);
+        }
+        for id in 0..graph.nodes().len() {
+            let node = graph.node(id);
+            let Some(parent) = node.parent else { continue };
+            velocities[id] = velocities[parent];
+            if let Some(orbit) = &node.orbit {
+                let mu = physics.gravitational_constant * (masses[parent] + masses[id]);
+                velocities[id] += orbital_velocity(orbit, mu, graph.node(parent));
+            }
+        }
+
+        let mut bodies = Vec::new();
+        for id in 0..graph.nodes().len() {
+            if id == ship_node || masses[id] == 0.0 {
+                continue;
+            }
+            let node = graph.node_mut(id);
+            let position = node.world_position();
+            node.simulated_position = Some(position);
+            let particle = system.add(Particle::new(masses[id], position, velocities[id]));
+            bodies.push(SimulatedBody { node: id, particle });
+        }
+
+        //marco del centro de masa: el sistema no se desplaza
+        let total_mass: f64 = system.particles.iter().map(|particle| particle.mass).sum();
+        if total_mass > 0.0 {
+            let drift = system.diagnostics().momentum / total_mass;
+            for particle in system.particles.iter_mut() {
+                particle.velocity -= drift;
+            }
+        }
+
+        let ship = physics
+            .ship_gravity
+            .then(|| system.add(Particle::new(0.0, spaceship.position, spaceship.velocity)));
+
+        graph.update_world_matrices();
+        let initial = system.diagnostics();
+
+        Ok(Gravity {
+            system,
+            steps: FixedStep::new(physics.timestep),
+            bodies,
+            ship,
+            initial,
+        })
+    }
+
+    fn advance(&mut self, seconds: f32, graph: &mut SceneGraph, spaceship: &mut Spaceship) {
+        //la nave pudo acelerar con los controles desde el ultimo frame
+        if let Some(ship) = self.ship {
+            self.system.particles[ship] = Particle::new(0.0, spaceship.position, spaceship.velocity);
+        }
+
+        let steps = self.steps.accumulate(seconds as f64);
+        let dt = self.steps.step.copysign(steps as f64);
+        for _ in 0..steps.unsigned_abs() {
+            self.system.step(dt);
+        }
+
+        for body in &self.bodies {
+            graph.node_mut(body.node).simulated_position = Some(self.system.particles[body.particle].position_f32());
+        }
+
+        let Some(ship) = self.ship else { return };
+        let particle = self.system.particles[ship];
+        spaceship.position = particle.position_f32();
+        spaceship.velocity = particle.velocity_f32();
+    }
+
+    //la nave es una particula de la simulacion (`ship_gravity`)
+    pub fn moves_ship(&self) -> bool {
+        self.ship.is_some()
+    }
+
+    pub fn body_velocity(&self, node: NodeId) -> Option<Vec3> {
+        let body = self.bodies.iter().find(|body| body.node == node)?;
+        Some(self.system.particles[body.particle].velocity_f32())
+    }
+
+    //diagnostico actual y deriva relativa de la energia desde el inicio
+    pub fn diagnostics(&self) -> (Diagnostics, f64) {
+        let current = self.system.diagnostics();
+        let initial_energy = self.initial.total_energy();
+        let drift = if initial_energy != 0.0 {
+            (current.total_energy() - initial_energy) / initial_energy.abs()
+        } else {
+            0.0
+        };
+        (current, drift)
+    }
+}
+
+fn face_culling(double_sided: bool) -> FaceCulling {
+    if double_sided {
+        FaceCulling::None
+    } else {
+        FaceCulling::Back
+    }
+}
+
+//velocidad de mundo sobre la orbita (vis-viva), en la direccion del movimiento
+fn orbital_velocity(orbit: &Orbit, mu: f64, parent: &Node) -> Vec3 {
+    let position = orbit.position();
+    let distance = position.norm() as f64;
+    if mu <= 0.0 || distance == 0.0 {
+        return Vec3::zeros();
+    }
+    let speed = (mu * (2.0 / distance - 1.0 / orbit.semi_major_axis as f64)).max(0.0).sqrt() as f32;
+
+    let mut ahead = orbit.clone();
+    ahead.mean_anomaly_at_epoch += 1e-3 * orbit.mean_motion.signum();
+    let direction = mat4_to_mat3(parent.world_matrix()) * (ahead.position() - position);
+    if direction.norm() == 0.0 {
+        return Vec3::zeros();
+    }
+    direction.normalize() * speed
+}
+
+#[derive(Default)]
+struct ModelCache {
+    models: HashMap<String, Arc<Model>>,
+    optimize: bool,
+}
+
+impl ModelCache {
+    fn load(&mut self, path: &str) -> Result<Arc<Model>, SceneError> {
+        if let Some(model) = self.models.get(path) {
+            return Ok(model.clone());
+        }
+        if !Path::new(path).exists() {
+            return Err(SceneError::Invalid(format!("model not found: {}", path)));
+        }
+        let obj = Obj::load(path).map_err(|err| SceneError::Model(path.to_string(), err))?;
+        let mut mesh = obj.get_mesh();
+        if self.optimize {
+            mesh.optimize_vertex_cache();
+        }
+        let model = Arc::new(Model::new(mesh));
+        self.models.insert(path.to_string(), model.clone());
+        Ok(model)
+    }
+}
+
+//misma imagen con el mismo muestreo se carga una sola vez
+#[derive(Default)]
+struct TextureCache {
+    textures: HashMap<(String, Sampler, bool), Arc<Texture>>,
+    //filtro impuesto por el preset a todas las texturas
+    filter: Option<Filter>,
+}
+
+impl TextureCache {
+    fn load(&mut self, description: &TexturesDescription) -> Result<TextureSet, SceneError> {
+        let sampler = Sampler {
+            filter: self.filter.unwrap_or(description.filter),
+            wrap: description.wrap,
+            mipmaps: description.mipmaps,
+        };
+        let mut load = |path: &Option<String>, srgb: bool| -> Result<Option<Arc<Texture>>, SceneError> {
+            let Some(path) = path else { return Ok(None) };
+            let key = (path.clone(), sampler, srgb);
+            if let Some(texture) = self.textures.get(&key) {
+                return Ok(Some(texture.clone()));
+            }
+            let texture = Arc::new(
+                Texture::load(path, sampler, srgb).map_err(|err| SceneError::Texture(path.clone(), err))?,
+            );
+            self.textures.insert(key, texture.clone());
+            Ok(Some(texture))
+        };
+        Ok(TextureSet {
+            albedo: load(&description.albedo, true)?,
+            normal: load(&description.normal, false)?,
+            specular: load(&description.specular, false)?,
+            night: load(&description.night, true)?,
+        })
+    }
+}
+
+fn build_orbit(name: &str, description: &OrbitDescription) -> Result<Orbit, SceneError> {
+    let values = [
+        description.semi_major_axis,
+        description.eccentricity,
+        description.inclination,
+        description.longitude_of_ascending_node,
+        description.argument_of_periapsis,
+        description.mean_anomaly,
+        description.speed,
+    ];
+    if values.iter().any(|value| !value.is_finite()) || description.semi_major_axis < 0.0 {
+        return Err(SceneError::Invalid(format!("{}: invalid orbit", name)));
+    }
+    //solo orbitas cerradas
+    if !(0.0..1.0).contains(&description.eccentricity) {
+        return Err(SceneError::Invalid(format!("{}: eccentricity must be in [0, 1)", name)));
+    }
+
+    Ok(Orbit {
+        semi_major_axis: description.semi_major_axis,
+        eccentricity: description.eccentricity,
+        inclination: description.inclination,
+        longitude_of_ascending_node: description.longitude_of_ascending_node,
+        argument_of_periapsis: description.argument_of_periapsis,
+        mean_anomaly_at_epoch: description.mean_anomaly,
+        mean_motion: description.speed,
+        elapsed: 0.0,
+    })
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn build_lights(description: &LightingDescription) -> Result<Vec<Light>, SceneError> {
+    if !non_negative(description.ambient) {
+        return Err(SceneError::Invalid("ambient light must be non-negative".to_string()));
+    }
+    let mut lights = vec![Light::Ambient {
+        color: light_color(&description.ambient_color)?,
+        intensity: description.ambient,
+    }];
+    for light in &description.directional {
+        let direction = to_vec3(light.direction);
+        if !non_negative(light.intensity) || direction.norm() == 0.0 || !direction.norm().is_finite() {
+            return Err(SceneError::Invalid("directional light needs a direction and non-negative intensity".to_string()));
+        }
+        lights.push(Light::Directional {
+            direction: direction.normalize(),
+            color: light_color(&light.color)?,
+            intensity: light.intensity,
+        });
+    }
+    Ok(lights)
+}
+
+//los cuerpos que puede mirar la camara: todo lo que se dibuja menos la nave y sus piezas
+fn build_camera_rig(
+    description: &CameraDescription,
+    graph: &SceneGraph,
+    ship_node: NodeId,
+    camera: Camera,
+) -> Result<CameraRig, SceneError> {
+    let rig = &description.rig;
+    let positive = [rig.chase_distance, rig.stiffness, rig.blend_seconds, rig.free_speed];
+    let fov_ok = rig.fov > 0.0 && non_negative(rig.speed_fov) && rig.fov + rig.speed_fov < 180.0;
+    if !(fov_ok && positive.into_iter().all(|value| value > 0.0 && value.is_finite())) {
+        return Err(SceneError::Invalid("camera rig distances, rates and fov must be positive".to_string()));
+    }
+    let targets = graph
+        .nodes()
+        .iter()
+        .enumerate()
+        .filter(|(id, node)| node.renderable.is_some() && !on_ship(graph, ship_node, *id))
+        .map(|(id, node)| (node.name.clone(), id))
+        .collect();
+    let mut camera_rig = CameraRig::new(description.mode, *rig, targets, camera);
+    if let Some(target) = &description.target {
+        if !camera_rig.set_target(target) {
+            return Err(SceneError::Invalid(format!("camera target not found: {}", target)));
+        }
+    }
+    match &description.track {
+        Some(path) => camera_rig.set_track(CameraTrack::load(path).map_err(|err| SceneError::Track(path.clone(), err))?),
+        None if description.mode == CameraMode::Track => {
+            return Err(SceneError::Invalid("camera mode track needs a track file".to_string()));
+        }
+        None => {}
+    }
+    Ok(camera_rig)
+}
+
+//la nave o una pieza colgada de ella
+fn on_ship(graph: &SceneGraph, ship_node: NodeId, mut id: NodeId) -> bool {
+    loop {
+        if id == ship_node {
+            return true;
+        }
+        match graph.node(id).parent {
+            Some(parent) => id = parent,
+            None => return false,
+        }
+    }
+}
+
+fn build_collision(collision: &CollisionSettings) -> Result<CollisionSettings, SceneError> {
+    if !(0.0..=1.0).contains(&collision.restitution) {
+        return Err(SceneError::Invalid("ship collision restitution must be between 0 and 1".to_string()));
+    }
+    Ok(*collision)
+}
+
+//en modo nbody la nave arranca en coasting: el frenado pelearia con las orbitas
+fn build_flight(flight: &FlightModel, mode: PhysicsMode) -> Result<FlightModel, SceneError> {
+    let rates = [flight.thrust, flight.drag, flight.angular_acceleration, flight.angular_drag];
+    if !rates.into_iter().all(non_negative) {
+        return Err(SceneError::Invalid("ship thrust, drag and angular rates must be non-negative".to_string()));
+    }
+    if !(flight.max_speed > 0.0 && flight.max_angular_speed > 0.0 && flight.boost >= 1.0) {
+        return Err(SceneError::Invalid("ship max speeds must be positive and boost at least 1".to_string()));
+    }
+    Ok(FlightModel {
+        coasting: flight.coasting || mode == PhysicsMode::Nbody,
+        ..*flight
+    })
+}
+
+fn build_hdr(description: &HdrDescription) -> Result<HdrSettings, SceneError> {
+    if !(description.exposure > 0.0 && description.exposure.is_finite()) {
+        return Err(SceneError::Invalid("exposure must be positive".to_string()));
+    }
+    if !non_negative(description.bloom_threshold) || !non_negative(description.bloom_intensity) {
+        return Err(SceneError::Invalid("bloom threshold and intensity must be non-negative".to_string()));
+    }
+    Ok(HdrSettings {
+        exposure: description.exposure,
+        tone_mapping: description.tone_mapping,
+        bloom: description.bloom.then_some(Bloom {
+            threshold: description.bloom_threshold,
+            intensity: description.bloom_intensity,
+            radius: description.bloom_radius,
+        }),
+    })
+}
+
+fn build_starfield(description: &BackgroundDescription) -> Result<Starfield, SceneError> {
+    let values = [description.magnitude_limit, description.star_brightness, description.milky_way, description.nebula];
+    if !values.into_iter().all(non_negative) || !(0.0..=1.0).contains(&description.twinkle) {
+        return Err(SceneError::Invalid(
+            "background star settings must be non-negative and twinkle between 0 and 1".to_string(),
+        ));
+    }
+    let catalogue = match &description.catalogue {
+        Some(path) => {
+            let source = std::fs::read_to_string(path).map_err(|err| SceneError::Io(path.clone(), err))?;
+            parse_catalogue(&source).map_err(|err| SceneError::Invalid(format!("{}: {}", path, err)))?
+        }
+        None => Vec::new(),
+    };
+    let settings = StarfieldSettings {
+        color: light_color(&description.color)?,
+        stars: description.stars,
+        seed: description.seed,
+        magnitude_limit: description.magnitude_limit,
+        brightness: description.star_brightness,
+        twinkle: description.twinkle,
+        milky_way: description.milky_way,
+        nebula: description.nebula,
+    };
+    Ok(Starfield::generate(&settings, &catalogue))
+}
+
+fn build_post(description: &[PostEffectDescription], preset: Preset) -> Result<PostProcess, SceneError> {
+    let mut passes: Vec<Pass> = description
+        .iter()
+        .map(|pass| {
+            pass.effect.validate().map_err(SceneError::Invalid)?;
+            Ok(Pass { effect: pass.effect, enabled: pass.enabled })
+        })
+        .collect::<Result<_, SceneError>>()?;
+
+    //el preset necesita dithering: prende el de la escena (con los bits del
+    //preset si pide mas) o agrega uno al final
+    if let Some(bits) = preset.color_bits() {
+        let scene_dither = passes.iter_mut().find_map(|pass| match &mut pass.effect {
+            Effect::Dither { bits } => Some((&mut pass.enabled, bits)),
+            _ => None,
+        });
+        match scene_dither {
+            Some((enabled, scene_bits)) => {
+                *enabled = true;
+                *scene_bits = (*scene_bits).min(bits);
+            }
+            None => passes.push(Pass { effect: Effect::Dither { bits }, enabled: true }),
+        }
+    }
+    Ok(PostProcess { passes })
+}
+
+fn build_point_light(name: &str, description: &PointLightDescription) -> Result<Light, SceneError> {
+    if !non_negative(description.intensity) || !non_negative(description.attenuation) {
+        return Err(SceneError::Invalid(format!("{}: light intensity and attenuation must be non-negative", name)));
+    }
+    Ok(Light::Point {
+        position: Vec3::zeros(),
+        color: light_color(&description.color)?,
+        intensity: description.intensity,
+        attenuation: description.attenuation,
+    })
+}
+
+fn non_negative(value: f32) -> bool {
+    value >= 0.0 && value.is_finite()
+}
+
+//"#RRGGBB" (sRGB) -> canales en luz lineal
+fn light_color(color: &str) -> Result<Vec3, SceneError> {
+    Ok(hex_to_linear(parse_color(color)?))
+}
+
+//"#RRGGBB" -> 0xRRGGBB
+fn parse_color(color: &str) -> Result<u32, SceneError> {
+    parse_hex(color).ok_or_else(|| SceneError::Invalid(format!("invalid color: {}", color)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+        [camera]
+        eye = [0.0, 0.0, 10.0]
+        [ship]
+        model = "assets/models/nave.obj"
+        shader = "spaceship_shader"
+        [[bodies]]
+        name = "sun"
+        model = "assets/models/sphere.obj"
+        shader = "lava_shader"
+    "#;
+
+    fn build(source: &str) -> Result<Scene, SceneError> {
+        let description: SceneDescription = toml::from_str(source).map_err(SceneError::Parse)?;
+        Scene::build(&description, &ShaderRegistry::default())
+    }
+
+    fn invalid(source: &str) -> String {
+        match build(source) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("scene should not build"),
+        }
+    }
+
+    #[test]
+    fn builds_minimal_scene() {
+        let scene = build(MINIMAL).unwrap();
+        assert!(scene.graph.find("sun").is_some());
+        assert_eq!(scene.graph.node(scene.ship_node).name, SHIP_NODE);
+    }
+
+    #[test]
+    fn rejects_bad_bodies() {
+        let moon = |parent: &str| {
+            format!("[[bodies]]\nname = \"moon\"\nmodel = \"assets/models/sphere.obj\"\nshader = \"lava_shader\"\nparent = \"{}\"\n", parent)
+        };
+        let duplicate = MINIMAL.to_string() + &moon("sun") + &moon("sun");
+        assert!(invalid(&duplicate).contains("duplicate body name: moon"));
+        //el padre tiene que estar declarado antes
+        let early = MINIMAL.replace("[[bodies]]", &(moon("sun") + "[[bodies]]"));
+        assert!(invalid(&early).contains("parent sun must be declared before it"));
+        let unknown = MINIMAL.replace("lava_shader", "plasma_shader");
+        assert!(invalid(&unknown).starts_with("sun: "));
+    }
+
+    #[test]
+    fn ship_flies_in_nbody_without_ship_gravity() {
+        let thrust = ShipInput { thrust: Vec3::new(0.0, 0.0, 1.0), ..ShipInput::default() };
+        for ship_gravity in [false, true] {
+            let source = format!("[physics]\nmode = \"nbody\"\nship_gravity = {}\n{}", ship_gravity, MINIMAL);
+            let mut scene = build(&source).unwrap();
+            let start = scene.spaceship.position;
+            for _ in 0..30 {
+                scene.fly_ship(&thrust, 1.0 / 60.0);
+                scene.update(1.0 / 60.0);
+            }
+            //sin gravedad en la nave la mueven los controles; con ella, la simulacion
+            let moved = scene.spaceship.position - start;
+            assert!(moved.dot(&scene.spaceship.forward()) > 0.1, "{} {}", ship_gravity, moved);
+        }
+    }
+
+    #[test]
+    fn orbiting_body_hits_parked_ship() {
+        //el planeta sale de (10, 0, 0) hacia +z a 5 unidades por segundo; la nave espera en su orbita
+        let source = r#"
+            [camera]
+            eye = [0.0, 20.0, 0.0]
+            [ship]
+            model = "assets/models/nave.obj"
+            shader = "spaceship_shader"
+            position = [9.71, 0.0, 2.4]
+            [[bodies]]
+            name = "sun"
+            model = "assets/models/sphere.obj"
+            shader = "lava_shader"
+            scale = 0.1
+            [[bodies]]
+            name = "planet"
+            model = "assets/models/sphere.obj"
+            shader = "lava_shader"
+            parent = "sun"
+            orbit = { radius = 10.0, speed = 0.5 }
+        "#;
+        let mut scene = build(source).unwrap();
+        let planet = scene.graph.find("planet").unwrap();
+        //un paso largo: el planeta avanza mas que su diametro y sin barrerlo pasaria a traves de la nave
+        scene.update(1.0);
+        let events = scene.take_collisions();
+        assert_eq!(events.first().map(|event| event.node), Some(planet), "{:?}", events);
+        assert!(events[0].speed > 3.0, "{}", events[0].speed);
+        //el planeta la arrastra con el en vez de dejarla atras o adentro
+        let body = scene.bodies.iter().find(|body| body.node == planet).unwrap();
+        assert!((scene.spaceship.position - body.center).norm() > body.radius, "{}", scene.spaceship.position);
+        assert!(scene.spaceship.position.z > 4.0, "{}", scene.spaceship.position);
+        assert!(scene.spaceship.velocity.dot(&body.motion.normalize()) > 3.0, "{}", scene.spaceship.velocity);
+    }
+
+    #[test]
+    fn n64_preset_keeps_its_color_depth() {
+        let dither = |source: &str| {
+            let passes = build(source).unwrap().post.passes;
+            let dithers: Vec<&Pass> = passes.iter().filter(|pass| matches!(pass.effect, Effect::Dither { .. })).collect();
+            assert_eq!(dithers.len(), 1);
+            (dithers[0].effect, dithers[0].enabled)
+        };
+        let n64 = format!("[render]\npreset = \"n64\"\n{}", MINIMAL);
+        assert_eq!(dither(&n64), (Effect::Dither { bits: 5 }, true));
+        //el de la escena se prende y baja a 5 bits; si ya pide menos, queda
+        for (bits, expected) in [(8, 5), (3, 3)] {
+            let post = format!("[[post]]\neffect = \"dither\"\nbits = {}\nenabled = false\n", bits);
+            assert_eq!(dither(&(n64.clone() + &post)), (Effect::Dither { bits: expected }, true));
+        }
+    }
+
+    #[test]
+    fn textures_are_loaded_once_and_shared() {
+        let path = std::env::temp_dir().join("spacetravel_scene_texture_test.png");
+        image::RgbImage::from_pixel(2, 2, image::Rgb([200, 100, 50])).save(&path).unwrap();
+        let path = path.to_str().unwrap();
+        let body = |name: &str, wrap: &str| {
+            format!(
+                "[[bodies]]\nname = \"{}\"\nmodel = \"assets/models/sphere.obj\"\nshader = \"textured_shader\"\ntextures = {{ albedo = '{}', wrap = \"{}\" }}\n",
+                name, path, wrap
+            )
+        };
+        let source = MINIMAL.to_string() + &body("a", "repeat") + &body("b", "repeat") + &body("c", "clamp");
+        let scene = build(&source);
+        std::fs::remove_file(path).ok();
+        let scene = scene.unwrap();
+
+        let albedo = |name: &str| {
+            let node = scene.graph.node(scene.graph.find(name).unwrap());
+            node.renderable.as_ref().unwrap().textures.albedo.clone().unwrap()
+        };
+        //misma imagen y muestreo: una sola textura; otro muestreo carga otra
+        assert!(Arc::ptr_eq(&albedo("a"), &albedo("b")));
+        assert!(!Arc::ptr_eq(&albedo("a"), &albedo("c")));
+        assert_eq!(albedo("c").width(), 2);
+    }
+
+    #[test]
+    fn rejects_bad_colors() {
+        let background = format!("[background]\ncolor = \"#12345G\"\n{}", MINIMAL);
+        assert!(invalid(&background).contains("invalid color: #12345G"));
+        let light = MINIMAL.to_string() + "light = { color = \"white\" }\n";
+        assert!(invalid(&light).contains("invalid color: white"));
+    }
+}
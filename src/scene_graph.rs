@@ -0,0 +1,196 @@
+use nalgebra_glm::{Mat4, Vec3};
+use std::sync::Arc;
+
+use crate::bvh::MeshBvh;
+use crate::create_model_matrix;
+use crate::culling::FaceCulling;
+use crate::mesh::Mesh;
+use crate::orbit::Orbit;
+use crate::shaders::{ShaderParams, ShaderProgram};
+use crate::texture::TextureSet;
+
+pub type NodeId = usize;
+
+//Transformacion local, relativa al padre
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Vec3,
+}
+
+impl Transform {
+    pub fn new(translation: Vec3, rotation: Vec3) -> Self {
+        Transform { translation, rotation }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        create_model_matrix(self.translation, 1.0, self.rotation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::new(Vec3::zeros(), Vec3::zeros())
+    }
+}
+
+//Malla con su centro y radio, para dibujarla centrada en el nodo
+pub struct Model {
+    pub mesh: Mesh,
+    pub center: Vec3,
+    pub radius: f32,
+    //triangulos para rayos y choques, en espacio de modelo
+    pub bvh: MeshBvh,
+}
+
+impl Model {
+    pub fn new(mesh: Mesh) -> Self {
+        let mut min = Vec3::repeat(f32::INFINITY);
+        let mut max = Vec3::repeat(f32::NEG_INFINITY);
+        for vertex in &mesh.vertices {
+            min = min.inf(&vertex.position);
+            max = max.sup(&vertex.position);
+        }
+        let center = if mesh.vertices.is_empty() { Vec3::zeros() } else { (min + max) * 0.5 };
+        let radius = mesh
+            .vertices
+            .iter()
+            .map(|vertex| (vertex.position - center).norm())
+            .fold(0.0, f32::max);
+
+        let bvh = MeshBvh::from_mesh(&mesh);
+        Model { mesh, center, radius, bvh }
+    }
+}
+
+pub struct Renderable {
+    pub model: Arc<Model>,
+    pub shader: ShaderProgram,
+    pub params: ShaderParams,
+    //escala solo de la malla, los hijos no la heredan
+    pub scale: f32,
+    pub culling: FaceCulling,
+    pub textures: TextureSet,
+}
+
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub local: Transform,
+    pub orbit: Option<Orbit>,
+    //posicion de mundo que impone la simulacion de gravedad; ignora padre y orbita
+    pub simulated_position: Option<Vec3>,
+    pub renderable: Option<Renderable>,
+    world: Mat4,
+}
+
+impl Node {
+    pub fn new(name: &str, parent: Option<NodeId>, local: Transform) -> Self {
+        Node {
+            name: name.to_string(),
+            parent,
+            local,
+            orbit: None,
+            simulated_position: None,
+            renderable: None,
+            world: Mat4::identity(),
+        }
+    }
+
+    pub fn world_matrix(&self) -> &Mat4 {
+        &self.world
+    }
+
+    pub fn world_position(&self) -> Vec3 {
+        self.world.column(3).xyz()
+    }
+
+    //matriz para dibujar la malla: centrada en el nodo y escalada
+    pub fn mesh_matrix(&self) -> Option<Mat4> {
+        let renderable = self.renderable.as_ref()?;
+        let offset = create_model_matrix(-renderable.model.center * renderable.scale, renderable.scale, Vec3::zeros());
+        Some(self.world * offset)
+    }
+}
+
+//Nodos guardados en orden: el padre siempre va antes que sus hijos
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+}
+
+impl SceneGraph {
+    pub fn add(&mut self, node: Node) -> NodeId {
+        if let Some(parent) = node.parent {
+            assert!(parent < self.nodes.len(), "parent node must be added first");
+        }
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id]
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn advance_orbits(&mut self, seconds: f32) {
+        for node in self.nodes.iter_mut() {
+            if let Some(orbit) = node.orbit.as_mut() {
+                orbit.advance(seconds);
+            }
+        }
+    }
+
+    //recalcula las matrices de mundo desde la raiz
+    pub fn update_world_matrices(&mut self) {
+        for i in 0..self.nodes.len() {
+            if let Some(orbit) = &self.nodes[i].orbit {
+                self.nodes[i].local.translation = orbit.position();
+            }
+
+            let node = &self.nodes[i];
+            let world = match (node.simulated_position, node.parent) {
+                (Some(position), _) => Transform::new(position, node.local.rotation).matrix(),
+                (None, Some(parent)) => self.nodes[parent].world * node.local.matrix(),
+                (None, None) => node.local.matrix(),
+            };
+            self.nodes[i].world = world;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn children_follow_rotated_translated_parent() {
+        let mut graph = SceneGraph::default();
+        //planeta en (10, 0, 0) girado un cuarto de vuelta en y: su +x apunta a -z del mundo
+        let planet = graph.add(Node::new("planet", None, Transform::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, FRAC_PI_2, 0.0))));
+        let moon = graph.add(Node::new("moon", Some(planet), Transform::new(Vec3::new(2.0, 0.0, 0.0), Vec3::zeros())));
+        let crater = graph.add(Node::new("crater", Some(moon), Transform::new(Vec3::new(0.0, 1.0, 0.5), Vec3::zeros())));
+        graph.update_world_matrices();
+
+        let close = |a: Vec3, b: Vec3| (a - b).norm() < 1e-5;
+        assert!(close(graph.node(moon).world_position(), Vec3::new(10.0, 0.0, -2.0)));
+        assert!(close(graph.node(crater).world_position(), Vec3::new(10.5, 1.0, -2.0)));
+
+        //al mover el padre los hijos lo siguen
+        graph.node_mut(planet).local.translation = Vec3::new(0.0, 3.0, 0.0);
+        graph.update_world_matrices();
+        assert!(close(graph.node(moon).world_position(), Vec3::new(0.0, 3.0, -2.0)));
+    }
+}
@@ -4,9 +4,196 @@ use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragment::Fragment;
 use crate::color::Color;
+use crate::lighting::{Material, Shading};
+use crate::texture::Texture;
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+//Etapa de vertices: de espacio de modelo a pantalla
+pub trait VertexShader: Send + Sync {
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex;
+}
+
+//Etapa de fragmentos: color final de cada pixel
+pub trait FragmentShader: Send + Sync {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color;
+}
+
+impl<F> VertexShader for F
+where
+    F: Fn(&Vertex, &Uniforms) -> Vertex + Send + Sync,
+{
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        self(vertex, uniforms)
+    }
+}
+
+impl<F> FragmentShader for F
+where
+    F: Fn(&Fragment, &Uniforms) -> Color + Send + Sync,
+{
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        self(fragment, uniforms)
+    }
+}
+
+//Parametros por objeto (desde el archivo de escena)
+pub type ShaderParams = HashMap<String, f32>;
+
+fn param(uniforms: &Uniforms, name: &str, default: f32) -> f32 {
+    uniforms.params.get(name).copied().unwrap_or(default)
+}
+
+//muestrea un mapa del objeto en el fragmento, si lo tiene
+fn sample(texture: &Option<Arc<Texture>>, fragment: &Fragment) -> Option<Vec3> {
+    texture
+        .as_ref()
+        .map(|texture| texture.sample(&fragment.tex_coords, fragment.uv_footprint))
+}
+
+//normal del normal map pasada a mundo con la base tangente del fragmento
+fn mapped_normal(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    let Some(texel) = sample(&uniforms.textures.normal, fragment) else {
+        return fragment.normal;
+    };
+    let normal = fragment.normal;
+    let tangent = fragment.tangent.xyz() - normal * normal.dot(&fragment.tangent.xyz());
+    let Some(tangent) = tangent.try_normalize(f32::EPSILON) else {
+        return normal;
+    };
+    //la bitangente sigue a v, que crece hacia abajo en la imagen: el verde se invierte
+    let bitangent = normal.cross(&tangent) * fragment.tangent.w;
+    let local = texel * 2.0 - Vec3::repeat(1.0);
+    (tangent * local.x - bitangent * local.y + normal * local.z)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(normal)
+}
+
+//ilumina un albedo con las luces de la escena; `specular` y `shininess`
+//salen de los parametros del objeto. Usa los normal, specular y night maps
+//si el objeto los tiene. Con Gouraud la luz ya viene de los vertices.
+fn lit(fragment: &Fragment, uniforms: &Uniforms, albedo: Color) -> Color {
+    if uniforms.shading == Shading::Gouraud {
+        let diffuse = fragment.vertex_light;
+        return with_night_lights(albedo.modulate(&diffuse), &diffuse, fragment, uniforms);
+    }
+    let specular_map = sample(&uniforms.textures.specular, fragment).map_or(1.0, |texel| texel.x);
+    let material = Material {
+        specular: param(uniforms, "specular", 0.0) * specular_map,
+        shininess: param(uniforms, "shininess", 32.0),
+    };
+    let normal = mapped_normal(fragment, uniforms);
+    let (diffuse, specular) = uniforms
+        .lighting
+        .light_at(&fragment.world_position, &normal, &uniforms.camera_position, &material, uniforms.two_sided);
+    let color = albedo.modulate(&diffuse) + Color::new(255, 255, 255).modulate(&specular);
+    with_night_lights(color, &diffuse, fragment, uniforms)
+}
+
+fn with_night_lights(color: Color, diffuse: &Vec3, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    match sample(&uniforms.textures.night, fragment) {
+        //las luces se apagan a medida que llega el sol
+        Some(night) => {
+            let darkness = (1.0 - diffuse.max() * 2.0).clamp(0.0, 1.0);
+            color + Color::from_vec3(&(night * darkness))
+        }
+        None => color,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShaderError {
+    UnknownVertexShader(String),
+    UnknownFragmentShader(String),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::UnknownVertexShader(name) => write!(f, "unknown vertex shader: {}", name),
+            ShaderError::UnknownFragmentShader(name) => write!(f, "unknown fragment shader: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+//Par de shaders ya resuelto, listo para `render`
+#[derive(Clone)]
+pub struct ShaderProgram {
+    pub vertex: Arc<dyn VertexShader>,
+    pub fragment: Arc<dyn FragmentShader>,
+}
+
+pub struct ShaderRegistry {
+    vertex_shaders: HashMap<String, Arc<dyn VertexShader>>,
+    fragment_shaders: HashMap<String, Arc<dyn FragmentShader>>,
+}
+
+impl ShaderRegistry {
+    pub fn empty() -> Self {
+        ShaderRegistry {
+            vertex_shaders: HashMap::new(),
+            fragment_shaders: HashMap::new(),
+        }
+    }
+
+    pub fn register_vertex(&mut self, name: &str, shader: impl VertexShader + 'static) {
+        self.vertex_shaders.insert(name.to_string(), Arc::new(shader));
+    }
+
+    pub fn register_fragment(&mut self, name: &str, shader: impl FragmentShader + 'static) {
+        self.fragment_shaders.insert(name.to_string(), Arc::new(shader));
+    }
+
+    pub fn vertex(&self, name: &str) -> Result<Arc<dyn VertexShader>, ShaderError> {
+        self.vertex_shaders
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ShaderError::UnknownVertexShader(name.to_string()))
+    }
+
+    pub fn fragment(&self, name: &str) -> Result<Arc<dyn FragmentShader>, ShaderError> {
+        self.fragment_shaders
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ShaderError::UnknownFragmentShader(name.to_string()))
+    }
+
+    //vertex shader por defecto + fragment shader con ese nombre
+    pub fn program(&self, fragment: &str) -> Result<ShaderProgram, ShaderError> {
+        self.program_with(DEFAULT_VERTEX_SHADER, fragment)
+    }
+
+    pub fn program_with(&self, vertex: &str, fragment: &str) -> Result<ShaderProgram, ShaderError> {
+        Ok(ShaderProgram {
+            vertex: self.vertex(vertex)?,
+            fragment: self.fragment(fragment)?,
+        })
+    }
+}
+
+pub const DEFAULT_VERTEX_SHADER: &str = "vertex_shader";
+
+impl Default for ShaderRegistry {
+    //shaders incluidos en el proyecto
+    fn default() -> Self {
+        let mut registry = ShaderRegistry::empty();
+        registry.register_vertex(DEFAULT_VERTEX_SHADER, vertex_shader);
+        registry.register_fragment("lines_shader", lines_shader);
+        registry.register_fragment("lava_shader", lava_shader);
+        registry.register_fragment("gradient_shader", gradient_shader);
+        registry.register_fragment("continents_shader", continents_shader);
+        registry.register_fragment("spaceship_shader", spaceship_shader);
+        registry.register_fragment("another_shader", another_shader);
+        registry.register_fragment("textured_shader", textured_shader);
+        registry
+    }
+}
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     let position = Vec4::new(
@@ -16,47 +203,46 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         1.0
     );
 
-    let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
-
-    let w = transformed.w;
-    let transformed_position = Vec4::new(
-        transformed.x / w,
-        transformed.y / w,
-        transformed.z / w,
-        1.0
-    );
-
-    let screen_position = uniforms.viewport_matrix * transformed_position;
+    let world = uniforms.model_matrix * position;
+    //la division por w y el viewport los hace `render` despues de recortar
+    let clip_position = uniforms.projection_matrix * uniforms.view_matrix * world;
 
     let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
     let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
 
     let transformed_normal = normal_matrix * vertex.normal;
+    let tangent = model_mat3 * vertex.tangent.xyz();
+
+    //Gouraud: la luz difusa se calcula aca una vez por vertice, sin especular
+    let color = match uniforms.shading {
+        Shading::Pixel => vertex.color,
+        Shading::Gouraud => {
+            let (diffuse, _) = uniforms.lighting.light_at(
+                &world.xyz(),
+                &transformed_normal,
+                &uniforms.camera_position,
+                &Material::default(),
+                uniforms.two_sided,
+            );
+            Color::from_vec3(&diffuse)
+        }
+    };
 
     Vertex {
         position: vertex.position,
         normal: vertex.normal,
         tex_coords: vertex.tex_coords,
-        color: vertex.color,
-        transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
-        transformed_normal: transformed_normal
+        color,
+        tangent: vertex.tangent,
+        clip_position,
+        transformed_position: vertex.transformed_position,
+        world_position: world.xyz(),
+        transformed_normal,
+        transformed_tangent: Vec4::new(tangent.x, tangent.y, tangent.z, vertex.tangent.w),
     }
 }
-//shader a usar
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &str) -> Color {
-  match shader_type {
-    "lines_shader" => lines_shader(fragment, uniforms),
-    "lava_shader" => lava_shader(fragment, uniforms),
-    "gradient_shader"=> gradient_shader(fragment, uniforms),
-    "continents_shader" => continents_shader(fragment, uniforms),
-    "spaceship_shader" => spaceship_shader(fragment, uniforms),
-    "another_shader" => another_shader(fragment, uniforms),
-    _ => Color::new(0, 0, 0),
-  }
-}
-
 fn lines_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let seed = uniforms.time as f32 * fragment.vertex_position.y * fragment.vertex_position.x;
+    let seed = uniforms.time * 60.0 * fragment.vertex_position.y * fragment.vertex_position.x;
   
     let mut rng = StdRng::seed_from_u64(seed.abs() as u64);
   
@@ -68,7 +254,7 @@ fn lines_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       Color::new(188, 67, 67)
     };
   
-    color1_or_color2 * fragment.intensity
+    lit(fragment, uniforms, color1_or_color2)
 }
 
 fn lava_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -86,7 +272,7 @@ fn lava_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Base frequency and amplitude for the pulsating effect
     let base_frequency = 0.2;
     let pulsate_amplitude = 0.5;
-    let t = uniforms.time as f32 * 0.01;
+    let t = uniforms.time * 0.6;
   
     // Pulsate on the z-axis to change spot size
     let pulsate = (t * base_frequency).sin() * pulsate_amplitude;
@@ -108,23 +294,20 @@ fn lava_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Use lerp for color blending based on noise value
     let color = dark_color.lerp(&bright_color, noise_value);
     //Brillo
-    let glow_factor = 2.0; //Intensidas
+    let glow_factor = param(uniforms, "glow", 2.0); //Intensidas
     let glowing_color = color * glow_factor;
     let glow_edge = Color::new(198, 33, 0) * (1.0 - noise_value); // White edge for glow
-    let final_color = glowing_color + glow_edge;
-  
-    final_color
+    glowing_color + glow_edge
 }
 
 fn gradient_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let _ = uniforms;
   let gradient_start = Color::new(0, 0, 255); // Color 1
   let gradient_end = Color::new(255, 0, 0);   //Color 2
 
   let t = (fragment.vertex_position.y + 1.0) * 0.5;
   let color = gradient_start.lerp(&gradient_end, t);
 
-  color * fragment.intensity
+  lit(fragment, uniforms, color)
 }
 
 fn continents_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -133,7 +316,7 @@ fn continents_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let oy = 45.0;
   let x = fragment.vertex_position.x;
   let y = fragment.vertex_position.y;
-  let t = uniforms.time as f32 * 0.3; // Velocidad rotacion
+  let t = uniforms.time * 18.0; // Velocidad rotacion
 
   //Rotacion
   let noise_value = uniforms.noise.get_noise_2d(x * zoom + ox + t, y * zoom + oy);
@@ -141,7 +324,7 @@ fn continents_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let land_color = Color::new(34, 139, 34);
   let ocean_color = Color::new(0, 0, 255);
 
-  let land_threshold = 0.14;
+  let land_threshold = param(uniforms, "land_threshold", 0.14);
 
   let terrain_color = if noise_value > land_threshold {
       land_color
@@ -149,7 +332,7 @@ fn continents_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       ocean_color
   };
 
-  terrain_color * fragment.intensity
+  lit(fragment, uniforms, terrain_color)
 }
 
 fn another_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -166,7 +349,7 @@ fn another_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let hemisphere_factor = (fragment.vertex_position.y + 1.0) * 0.5;
   let blended_color = terrain_color.lerp(&Color::new(255, 222, 173), hemisphere_factor); // Arena clara
 
-  blended_color * fragment.intensity
+  lit(fragment, uniforms, blended_color)
 }
 
 fn spaceship_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -180,5 +363,67 @@ fn spaceship_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       + (fragment.vertex_position.y * 10.0).cos()) * 0.5 + 0.5;
   let patterned_color = blended_color * pattern;
 
-  patterned_color * fragment.intensity
-}
\ No newline at end of file
+  lit(fragment, uniforms, patterned_color)
+}
+
+//albedo map iluminado; sin textura queda blanco. `brightness` lo aclara u oscurece
+fn textured_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let albedo = sample(&uniforms.textures.albedo, fragment).unwrap_or(Vec3::repeat(1.0));
+  let brightness = param(uniforms, "brightness", 1.0);
+
+  lit(fragment, uniforms, Color::from_vec3(&(albedo * brightness)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passthrough(vertex: &Vertex, _: &Uniforms) -> Vertex {
+        vertex.clone()
+    }
+
+    fn red(_: &Fragment, _: &Uniforms) -> Color {
+        Color::new(255, 0, 0)
+    }
+
+    #[test]
+    fn looks_up_registered_shaders() {
+        let mut registry = ShaderRegistry::empty();
+        registry.register_vertex("passthrough", passthrough);
+        registry.register_fragment("red", red);
+
+        let program = registry.program_with("passthrough", "red").unwrap();
+        assert!(Arc::ptr_eq(&program.vertex, &registry.vertex("passthrough").unwrap()));
+        assert!(Arc::ptr_eq(&program.fragment, &registry.fragment("red").unwrap()));
+        //`program` usa el vertex shader por defecto, que este registro no tiene
+        assert_eq!(
+            registry.program("red").err(),
+            Some(ShaderError::UnknownVertexShader(DEFAULT_VERTEX_SHADER.to_string()))
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        let registry = ShaderRegistry::default();
+        let missing = registry.program("plasma_shader").err().unwrap();
+        assert_eq!(missing, ShaderError::UnknownFragmentShader("plasma_shader".to_string()));
+        assert_eq!(missing.to_string(), "unknown fragment shader: plasma_shader");
+        let missing = registry.program_with("wobble", "lava_shader").err().unwrap();
+        assert_eq!(missing.to_string(), "unknown vertex shader: wobble");
+    }
+
+    #[test]
+    fn program_with_overrides_one_stage() {
+        let mut registry = ShaderRegistry::default();
+        registry.register_vertex("passthrough", passthrough);
+        let default = registry.program("lava_shader").unwrap();
+        let custom = registry.program_with("passthrough", "lava_shader").unwrap();
+        assert!(Arc::ptr_eq(&custom.vertex, &registry.vertex("passthrough").unwrap()));
+        assert!(!Arc::ptr_eq(&custom.vertex, &default.vertex));
+        assert!(Arc::ptr_eq(&custom.fragment, &default.fragment));
+
+        //registrar otra vez el mismo nombre lo reemplaza
+        registry.register_fragment("lava_shader", red);
+        assert!(!Arc::ptr_eq(&registry.program("lava_shader").unwrap().fragment, &default.fragment));
+    }
+}
@@ -1,34 +1,225 @@
-use nalgebra::{Rotation3, Vector3, Unit};
+use nalgebra::{UnitQuaternion, Vector3};
 use nalgebra_glm::Vec3;
+use serde::Deserialize;
+
+//la nariz de la nave en espacio de modelo; arriba es +y y la derecha +x
+const NOSE: Vec3 = Vec3::new(0.0, 0.0, -1.0);
+
+//Como responde la nave a los controles
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlightModel {
+    //aceleracion de los propulsores, en unidades/s^2 (adelante, de lado y vertical)
+    pub thrust: f32,
+    //los propulsores no empujan mas alla de esta velocidad (la gravedad si)
+    pub max_speed: f32,
+    //multiplica empuje y velocidad maxima mientras se mantiene el boost
+    pub boost: f32,
+    //frenado por segundo sin coasting: la nave se detiene sola al soltar
+    pub drag: f32,
+    //en rad/s^2 y rad/s, para cabeceo, guinada y alabeo
+    pub angular_acceleration: f32,
+    pub max_angular_speed: f32,
+    //el giro siempre se amortigua, si no seria imposible apuntar
+    pub angular_drag: f32,
+    //sin frenado: la nave sigue con la velocidad que lleva (newtoniano)
+    pub coasting: bool,
+}
+
+impl Default for FlightModel {
+    fn default() -> Self {
+        FlightModel {
+            thrust: 12.0,
+            max_speed: 8.0,
+            boost: 2.0,
+            drag: 1.5,
+            angular_acceleration: 8.0,
+            max_angular_speed: 2.5,
+            angular_drag: 5.0,
+            coasting: false,
+        }
+    }
+}
+
+//Lo que pide el piloto en este frame, cada eje de -1 a 1
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ShipInput {
+    //derecha, arriba, adelante (ejes de la nave)
+    pub thrust: Vec3,
+    //cabeceo (nariz arriba), guinada (a la izquierda), alabeo (ala derecha abajo)
+    pub rotation: Vec3,
+    pub boost: bool,
+}
+
 pub struct Spaceship {
-    pub position: Vec3,   
-    pub forward: Vec3,     
-    pub up: Vec3,         
+    pub position: Vec3,
+    pub orientation: UnitQuaternion<f32>,
+    //en mundo
+    pub velocity: Vec3,
+    //en ejes de la nave (x = cabeceo, y = guinada, z = alabeo)
+    pub angular_velocity: Vec3,
+    pub flight: FlightModel,
 }
 
 impl Spaceship {
     pub fn new(start_position: Vec3) -> Self {
         Self {
             position: start_position,
-            forward: Vec3::new(0.0, 0.0, -1.3),
-            up: Vec3::new(0.0, 1.0, 0.0),       
+            orientation: UnitQuaternion::identity(),
+            velocity: Vec3::zeros(),
+            angular_velocity: Vec3::zeros(),
+            flight: FlightModel::default(),
         }
     }
 
-    pub fn move_forward(&mut self, distance: f32) {
-        self.position += self.forward * distance;
+    //apunta la nariz hacia `forward` (sin alabeo respecto a +y si se puede)
+    pub fn look_towards(&mut self, forward: &Vec3) {
+        let Some(forward) = forward.try_normalize(f32::EPSILON) else { return };
+        let up = if forward.cross(&Vec3::y()).norm() > 1e-3 { Vec3::y() } else { Vec3::z() };
+        //face_towards deja +z del modelo hacia donde se le pide; la nariz es -z
+        self.orientation = UnitQuaternion::face_towards(&to_vector(&-forward), &to_vector(&up));
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        from_vector(&(self.orientation * to_vector(&NOSE)))
     }
 
-    pub fn rotate(&mut self, angle: f32) {
-        let up = Vector3::new(self.up.x, self.up.y, self.up.z);
-        let forward = Vector3::new(self.forward.x, self.forward.y, self.forward.z);
+    pub fn up(&self) -> Vec3 {
+        from_vector(&(self.orientation * Vector3::y()))
+    }
+
+    pub fn right(&self) -> Vec3 {
+        from_vector(&(self.orientation * Vector3::x()))
+    }
+
+    //rotacion del nodo para `create_model_matrix` (Rz * Ry * Rx)
+    pub fn euler_angles(&self) -> Vec3 {
+        let (roll, pitch, yaw) = self.orientation.euler_angles();
+        Vec3::new(roll, pitch, yaw)
+    }
+
+    //aplica los controles a la orientacion y la velocidad; la posicion la
+    //mueve `advance` (o la simulacion de gravedad)
+    pub fn steer(&mut self, input: &ShipInput, seconds: f32) {
+        let flight = self.flight;
+        let boost = if input.boost { flight.boost } else { 1.0 };
 
-        let unit_up = Unit::new_normalize(up);
-        let rotation = Rotation3::from_axis_angle(&unit_up, angle);
-        let rotated_forward = rotation * forward; 
-        let rotated_up = rotation * up;
+        let spin = clamp_axes(&input.rotation) * flight.angular_acceleration;
+        self.angular_velocity += spin * seconds;
+        self.angular_velocity *= (-flight.angular_drag * seconds).exp();
+        self.angular_velocity = limit(&self.angular_velocity, flight.max_angular_speed);
+        //la velocidad angular esta en ejes de la nave: se compone a la derecha
+        //(el alabeo gira alrededor de la nariz, que es -z)
+        let turn = Vec3::new(self.angular_velocity.x, self.angular_velocity.y, -self.angular_velocity.z) * seconds;
+        self.orientation *= UnitQuaternion::from_scaled_axis(to_vector(&turn));
+
+        let thrust = clamp_axes(&input.thrust);
+        let acceleration = (self.right() * thrust.x + self.up() * thrust.y + self.forward() * thrust.z)
+            * (flight.thrust * boost);
+        let speed = self.velocity.norm();
+        self.velocity += acceleration * seconds;
+        //el empuje no pasa de la velocidad maxima, pero no frena lo que ya iba mas rapido
+        self.velocity = limit(&self.velocity, speed.max(flight.max_speed * boost));
+
+        if !flight.coasting {
+            self.velocity *= (-flight.drag * seconds).exp();
+        }
+    }
+
+    //donde estara en `seconds` con la velocidad actual
+    pub fn next_position(&self, seconds: f32) -> Vec3 {
+        self.position + self.velocity * seconds
+    }
 
-        self.forward = Vec3::new(rotated_forward.x, rotated_forward.y, rotated_forward.z);
-        self.up = Vec3::new(rotated_up.x, rotated_up.y, rotated_up.z);
+    pub fn advance(&mut self, seconds: f32) {
+        self.position = self.next_position(seconds);
     }
-}
\ No newline at end of file
+}
+
+fn clamp_axes(axes: &Vec3) -> Vec3 {
+    axes.map(|axis| axis.clamp(-1.0, 1.0))
+}
+
+fn limit(vector: &Vec3, max: f32) -> Vec3 {
+    let norm = vector.norm();
+    if norm > max && norm > 0.0 {
+        vector * (max / norm)
+    } else {
+        *vector
+    }
+}
+
+fn to_vector(vector: &Vec3) -> Vector3<f32> {
+    Vector3::new(vector.x, vector.y, vector.z)
+}
+
+fn from_vector(vector: &Vector3<f32>) -> Vec3 {
+    Vec3::new(vector.x, vector.y, vector.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_model_matrix;
+
+    fn fly(ship: &mut Spaceship, input: &ShipInput, seconds: f32) {
+        for _ in 0..(seconds * 60.0) as usize {
+            ship.steer(input, 1.0 / 60.0);
+            ship.advance(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn thrust_drag_and_coasting() {
+        let mut ship = Spaceship::new(Vec3::zeros());
+        let forward = ShipInput { thrust: Vec3::z(), ..ShipInput::default() };
+        fly(&mut ship, &forward, 3.0);
+        let cruise = ship.velocity.norm();
+        assert!(cruise <= ship.flight.max_speed + 1e-4, "{}", cruise);
+        assert!(ship.position.z < -5.0);
+
+        //con boost se pasa de la velocidad maxima normal
+        fly(&mut ship, &ShipInput { boost: true, ..forward }, 3.0);
+        assert!(ship.velocity.norm() > ship.flight.max_speed * 1.2);
+
+        //al soltar, el frenado la detiene; con coasting sigue igual
+        let mut coasting = Spaceship::new(Vec3::zeros());
+        coasting.flight.coasting = true;
+        coasting.velocity = Vec3::new(3.0, 0.0, 0.0);
+        fly(&mut ship, &ShipInput::default(), 4.0);
+        fly(&mut coasting, &ShipInput::default(), 4.0);
+        assert!(ship.velocity.norm() < 0.1);
+        assert_eq!(coasting.velocity, Vec3::new(3.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn pitch_leaves_the_orbital_plane() {
+        let mut ship = Spaceship::new(Vec3::zeros());
+        let input = ShipInput { rotation: Vec3::x(), thrust: Vec3::z(), boost: false };
+        fly(&mut ship, &input, 0.5);
+        //nariz arriba: sube
+        assert!(ship.forward().y > 0.2, "{}", ship.forward());
+        assert!(ship.position.y > 0.0);
+        //guinada a la izquierda y alabeo con el ala derecha abajo
+        let mut ship = Spaceship::new(Vec3::zeros());
+        fly(&mut ship, &ShipInput { rotation: Vec3::y(), ..ShipInput::default() }, 0.3);
+        assert!(ship.forward().x < -0.1, "{}", ship.forward());
+        let mut ship = Spaceship::new(Vec3::zeros());
+        fly(&mut ship, &ShipInput { rotation: Vec3::z(), ..ShipInput::default() }, 0.3);
+        assert!(ship.right().y < -0.1, "{}", ship.right());
+        assert!((ship.forward().norm() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn euler_angles_match_the_model_matrix() {
+        let mut ship = Spaceship::new(Vec3::zeros());
+        ship.look_towards(&Vec3::new(0.3, 0.5, -1.0));
+        ship.orientation *= UnitQuaternion::from_scaled_axis(Vector3::z() * 0.4);
+        let matrix = create_model_matrix(Vec3::zeros(), 1.0, ship.euler_angles());
+        let nose = (matrix * NOSE.push(0.0)).xyz();
+        let up = (matrix * Vec3::y().push(0.0)).xyz();
+        assert!((nose - ship.forward()).norm() < 1e-4, "{} {}", nose, ship.forward());
+        assert!((up - ship.up()).norm() < 1e-4);
+        assert!((ship.forward() - Vec3::new(0.3, 0.5, -1.0).normalize()).norm() < 1e-4);
+    }
+}
@@ -0,0 +1,476 @@
+use fastnoise_lite::{FastNoiseLite, FractalType, NoiseType};
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::PI;
+
+use crate::color::srgb_to_linear;
+
+//Cielo de fondo: estrellas en direcciones del mundo (estan en el infinito,
+//asi que solo las mueve el giro de la camara) y un cubemap con la Via Lactea
+//y nebulosas. Todo sale de `seed`: el mismo cielo en cada corrida.
+
+//Ejes en los que se dibujan el catalogo y la Via Lactea: ascension recta
+//alpha y declinacion delta van a (cos d cos a, sin d, cos d sin a), con y
+//hacia el polo norte celeste.
+pub fn equatorial_direction(right_ascension: f32, declination: f32) -> Vec3 {
+    let (alpha, delta) = (right_ascension.to_radians(), declination.to_radians());
+    Vec3::new(delta.cos() * alpha.cos(), delta.sin(), delta.cos() * alpha.sin())
+}
+
+//polo norte y centro galacticos (J2000), en grados
+const GALACTIC_POLE: (f32, f32) = (192.86, 27.13);
+const GALACTIC_CENTER: (f32, f32) = (266.40, -28.94);
+//grosor de la banda (desviacion de la latitud, en radianes)
+const BAND_WIDTH: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StarfieldSettings {
+    //fondo en luz lineal
+    pub color: Vec3,
+    //estrellas generadas (ademas de las del catalogo)
+    pub stars: usize,
+    pub seed: u64,
+    //magnitud de la estrella mas debil; las hay de 0 a esta, mas cuanto mas debiles
+    pub magnitude_limit: f32,
+    //luz de una estrella de magnitud 0 (las brillantes pasan de 1 y hacen bloom)
+    pub brightness: f32,
+    //cuanto varia el brillo de cada estrella en el tiempo (0 a 1)
+    pub twinkle: f32,
+    //intensidad del resplandor de la Via Lactea; 0 la apaga
+    pub milky_way: f32,
+    //intensidad de las nebulosas; 0 las apaga
+    pub nebula: f32,
+}
+
+impl Default for StarfieldSettings {
+    fn default() -> Self {
+        StarfieldSettings {
+            color: Vec3::zeros(),
+            stars: 0,
+            seed: 1,
+            magnitude_limit: 7.0,
+            brightness: 3.0,
+            twinkle: 0.0,
+            milky_way: 0.0,
+            nebula: 0.0,
+        }
+    }
+}
+
+//Estrella real: posicion en grados, magnitud visual e indice de color B-V
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CatalogueStar {
+    pub right_ascension: f32,
+    pub declination: f32,
+    pub magnitude: f32,
+    pub color_index: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Star {
+    direction: Vec3,
+    //color ya multiplicado por el brillo, en luz lineal
+    light: Vec3,
+    twinkle_rate: f32,
+    twinkle_phase: f32,
+}
+
+pub struct Starfield {
+    color: Vec3,
+    twinkle: f32,
+    stars: Vec<Star>,
+    sky: Option<SkyMap>,
+}
+
+impl Starfield {
+    pub fn generate(settings: &StarfieldSettings, catalogue: &[CatalogueStar]) -> Self {
+        let mut rng = StdRng::seed_from_u64(settings.seed);
+        let galaxy = GalacticFrame::new();
+        let mut stars = Vec::with_capacity(settings.stars + catalogue.len());
+
+        for _ in 0..settings.stars {
+            //con Via Lactea, la mitad de las estrellas se juntan en la banda
+            let direction = if settings.milky_way > 0.0 && rng.gen_bool(0.5) {
+                let longitude = rng.gen_range(0.0..2.0 * PI);
+                let latitude = (gaussian(&mut rng) * BAND_WIDTH).clamp(-PI / 2.0, PI / 2.0);
+                galaxy.direction(longitude, latitude)
+            } else {
+                random_direction(&mut rng)
+            };
+            let magnitude = random_magnitude(&mut rng, settings.magnitude_limit);
+            let color_index = random_color_index(&mut rng);
+            stars.push(Star {
+                direction,
+                light: star_color(color_index) * magnitude_light(magnitude, settings.brightness),
+                twinkle_rate: rng.gen_range(1.0..4.0),
+                twinkle_phase: rng.gen_range(0.0..2.0 * PI),
+            });
+        }
+
+        for entry in catalogue {
+            stars.push(Star {
+                direction: equatorial_direction(entry.right_ascension, entry.declination),
+                light: star_color(entry.color_index) * magnitude_light(entry.magnitude, settings.brightness),
+                twinkle_rate: rng.gen_range(1.0..4.0),
+                twinkle_phase: rng.gen_range(0.0..2.0 * PI),
+            });
+        }
+
+        let sky = (settings.milky_way > 0.0 || settings.nebula > 0.0)
+            .then(|| SkyMap::generate(settings, &galaxy));
+        Starfield { color: settings.color, twinkle: settings.twinkle, stars, sky }
+    }
+
+    pub fn star_count(&self) -> usize {
+        self.stars.len()
+    }
+
+    //dibuja el cielo en `background` (width x height, luz lineal). `sky_matrix`
+    //es proyeccion * vista sin la traslacion de la camara.
+    pub fn draw(&self, background: &mut [Vec3], width: usize, height: usize, sky_matrix: &Mat4, time: f32) {
+        match (&self.sky, sky_matrix.try_inverse()) {
+            (Some(sky), Some(inverse)) => {
+                //rayo de cada pixel: punto del plano lejano, lineal en pantalla
+                let corner = inverse * Vec4::new(-1.0, 1.0, 1.0, 1.0);
+                let step_x = inverse * Vec4::new(2.0 / width as f32, 0.0, 0.0, 0.0);
+                let step_y = inverse * Vec4::new(0.0, -2.0 / height as f32, 0.0, 0.0);
+                for y in 0..height {
+                    let row = corner + step_y * (y as f32 + 0.5);
+                    for x in 0..width {
+                        let ray = (row + step_x * (x as f32 + 0.5)).xyz();
+                        background[y * width + x] = self.color + sky.sample(&ray);
+                    }
+                }
+            }
+            _ => background.fill(self.color),
+        }
+
+        for star in &self.stars {
+            let clip = sky_matrix * star.direction.push(0.0);
+            //detras de la camara
+            if clip.w <= 0.0 {
+                continue;
+            }
+            //mismo viewport que `create_viewport_matrix`
+            let x = (clip.x / clip.w + 1.0) * 0.5 * width as f32;
+            let y = (1.0 - clip.y / clip.w) * 0.5 * height as f32;
+            let twinkle = 1.0 + self.twinkle * (time * star.twinkle_rate + star.twinkle_phase).sin();
+            splat(background, width, height, x, y, &(star.light * twinkle));
+        }
+    }
+}
+
+//reparte la luz entre los cuatro pixeles vecinos: la estrella se mueve suave
+//aunque avance menos de un pixel
+fn splat(background: &mut [Vec3], width: usize, height: usize, x: f32, y: f32, light: &Vec3) {
+    let (x, y) = (x - 0.5, y - 0.5);
+    if x <= -1.0 || y <= -1.0 || x >= width as f32 || y >= height as f32 {
+        return;
+    }
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    for (dx, dy, weight) in [(0, 0, (1.0 - tx) * (1.0 - ty)), (1, 0, tx * (1.0 - ty)), (0, 1, (1.0 - tx) * ty), (1, 1, tx * ty)] {
+        let (px, py) = (x0 + dx, y0 + dy);
+        if px >= 0 && py >= 0 && (px as usize) < width && (py as usize) < height {
+            background[py as usize * width + px as usize] += light * weight;
+        }
+    }
+}
+
+fn random_direction(rng: &mut StdRng) -> Vec3 {
+    let z: f32 = rng.gen_range(-1.0..1.0);
+    let angle = rng.gen_range(0.0..2.0 * PI);
+    let ring = (1.0 - z * z).sqrt();
+    Vec3::new(ring * angle.cos(), ring * angle.sin(), z)
+}
+
+//normal estandar (Box-Muller)
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let v: f32 = rng.gen_range(0.0..1.0);
+    (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+}
+
+//la cantidad de estrellas hasta magnitud m crece como 10^(0.6 m): se invierte
+//esa distribucion entre 0 y `limit`
+fn random_magnitude(rng: &mut StdRng, limit: f32) -> f32 {
+    let lowest = 10f32.powf(-0.6 * limit);
+    let u: f32 = rng.gen_range(0.0..1.0);
+    limit + (lowest + u * (1.0 - lowest)).log10() / 0.6
+}
+
+//B-V: pocas azules, la mayoria amarillas como el sol, algunas rojas
+fn random_color_index(rng: &mut StdRng) -> f32 {
+    match rng.gen_range(0..4) {
+        0 => rng.gen_range(-0.3..0.2),
+        3 => rng.gen_range(0.9..1.8),
+        _ => rng.gen_range(0.3..0.9),
+    }
+}
+
+//cada 5 magnitudes el brillo baja 100 veces
+fn magnitude_light(magnitude: f32, brightness: f32) -> f32 {
+    brightness * 10f32.powf(-0.4 * magnitude)
+}
+
+//indice B-V -> temperatura (Ballesteros) -> color de cuerpo negro
+//(aproximacion de Tanner Helland), normalizado a canal maximo 1
+fn star_color(color_index: f32) -> Vec3 {
+    let bv = color_index.clamp(-0.4, 2.0);
+    let kelvin = 4600.0 * (1.0 / (0.92 * bv + 1.7) + 1.0 / (0.92 * bv + 0.62));
+    let t = kelvin / 100.0;
+    let red = if t <= 66.0 { 255.0 } else { 329.698_73 * (t - 60.0).powf(-0.133_204_76) };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    let color = Vec3::new(red, green, blue).map(|channel| srgb_to_linear(channel.clamp(0.0, 255.0) / 255.0));
+    color / color.max()
+}
+
+//base con el polo galactico como y y el centro como x
+struct GalacticFrame {
+    pole: Vec3,
+    center: Vec3,
+    side: Vec3,
+}
+
+impl GalacticFrame {
+    fn new() -> Self {
+        let pole = equatorial_direction(GALACTIC_POLE.0, GALACTIC_POLE.1);
+        let center = equatorial_direction(GALACTIC_CENTER.0, GALACTIC_CENTER.1);
+        let center = (center - pole * pole.dot(&center)).normalize();
+        GalacticFrame { pole, center, side: pole.cross(&center) }
+    }
+
+    fn direction(&self, longitude: f32, latitude: f32) -> Vec3 {
+        (self.center * longitude.cos() + self.side * longitude.sin()) * latitude.cos() + self.pole * latitude.sin()
+    }
+}
+
+//texels por lado de cada cara del cubemap
+const SKY_RESOLUTION: usize = 128;
+
+//Cubemap con la luz difusa del cielo; caras +x, -x, +y, -y, +z, -z
+struct SkyMap {
+    faces: Vec<Vec3>,
+}
+
+impl SkyMap {
+    fn generate(settings: &StarfieldSettings, galaxy: &GalacticFrame) -> Self {
+        let fractal = |seed: i32, octaves: i32| {
+            let mut noise = FastNoiseLite::with_seed(seed);
+            noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+            noise.set_fractal_type(Some(FractalType::FBm));
+            noise.set_fractal_octaves(Some(octaves));
+            noise.set_frequency(Some(1.0));
+            noise
+        };
+        let seed = settings.seed as i32;
+        let (clouds, dust, nebula, hue) = (fractal(seed, 5), fractal(seed + 1, 4), fractal(seed + 2, 5), fractal(seed + 3, 2));
+        let noise = |noise: &FastNoiseLite, direction: &Vec3, scale: f32| {
+            noise.get_noise_3d(direction.x * scale, direction.y * scale, direction.z * scale)
+        };
+
+        let mut faces = Vec::with_capacity(6 * SKY_RESOLUTION * SKY_RESOLUTION);
+        for face in 0..6 {
+            for y in 0..SKY_RESOLUTION {
+                for x in 0..SKY_RESOLUTION {
+                    let u = (x as f32 + 0.5) / SKY_RESOLUTION as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / SKY_RESOLUTION as f32 * 2.0 - 1.0;
+                    let direction = face_direction(face, u, v).normalize();
+                    let mut light = Vec3::zeros();
+
+                    if settings.milky_way > 0.0 {
+                        let latitude = galaxy.pole.dot(&direction).asin();
+                        let band = (-0.5 * (latitude / BAND_WIDTH).powi(2)).exp();
+                        //mas brillante hacia el centro, con nubes y una franja de polvo
+                        let bulge = 1.0 + 2.0 * (-(1.0 - galaxy.center.dot(&direction)) * 8.0).exp();
+                        let cloudy = 0.6 + 0.6 * noise(&clouds, &direction, 6.0);
+                        let lane = 1.0 - 0.7 * (-0.5 * (latitude / (BAND_WIDTH * 0.25)).powi(2)).exp()
+                            * (0.5 + 0.5 * noise(&dust, &direction, 10.0));
+                        let glow = band * bulge * cloudy.max(0.0) * lane * settings.milky_way * 0.05;
+                        light += Vec3::new(1.0, 0.9, 0.75) * glow;
+                    }
+
+                    if settings.nebula > 0.0 {
+                        //solo donde el ruido pasa un umbral: manchas sueltas, no una niebla pareja
+                        let density = (noise(&nebula, &direction, 2.5) - 0.15).max(0.0) * 2.0;
+                        let tint = 0.5 + 0.5 * noise(&hue, &direction, 1.5);
+                        let color = Vec3::new(0.8, 0.1, 0.5).lerp(&Vec3::new(0.1, 0.3, 0.9), tint);
+                        light += color * (density * density * settings.nebula * 0.03);
+                    }
+
+                    faces.push(light);
+                }
+            }
+        }
+        SkyMap { faces }
+    }
+
+    //bilineal dentro de la cara (los bordes de cara se estiran)
+    fn sample(&self, direction: &Vec3) -> Vec3 {
+        let (face, u, v) = cube_coordinates(direction);
+        let size = SKY_RESOLUTION as f32;
+        let x = ((u + 1.0) * 0.5 * size - 0.5).clamp(0.0, size - 1.0);
+        let y = ((v + 1.0) * 0.5 * size - 0.5).clamp(0.0, size - 1.0);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(SKY_RESOLUTION - 1), (y0 + 1).min(SKY_RESOLUTION - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+        let texel = |x: usize, y: usize| self.faces[(face * SKY_RESOLUTION + y) * SKY_RESOLUTION + x];
+        let top = texel(x0, y0).lerp(&texel(x1, y0), tx);
+        let bottom = texel(x0, y1).lerp(&texel(x1, y1), tx);
+        top.lerp(&bottom, ty)
+    }
+}
+
+//punto de la cara `face` del cubo de lado 2 en (u, v), de -1 a 1
+fn face_direction(face: usize, u: f32, v: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        _ => Vec3::new(-u, -v, -1.0),
+    }
+}
+
+//inversa de `face_direction`: la cara es el eje mayor
+fn cube_coordinates(direction: &Vec3) -> (usize, f32, f32) {
+    let abs = direction.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        let (x, y, z) = (direction.x, direction.y, direction.z);
+        if x > 0.0 { (0, -z / abs.x, -y / abs.x) } else { (1, z / abs.x, -y / abs.x) }
+    } else if abs.y >= abs.z {
+        let (x, y, z) = (direction.x, direction.y, direction.z);
+        if y > 0.0 { (2, x / abs.y, z / abs.y) } else { (3, x / abs.y, -z / abs.y) }
+    } else {
+        let (x, y, z) = (direction.x, direction.y, direction.z);
+        if z > 0.0 { (4, x / abs.z, -y / abs.z) } else { (5, -x / abs.z, -y / abs.z) }
+    }
+}
+
+//CSV con encabezado `ra,dec,mag,bv` (grados, grados, magnitud visual, B-V);
+//`bv` puede faltar. Lineas vacias y las que empiezan con # se saltan.
+pub fn parse_catalogue(source: &str) -> Result<Vec<CatalogueStar>, String> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| columns.iter().position(|&column| column == name);
+    let (Some(ra), Some(dec), Some(mag)) = (column("ra"), column("dec"), column("mag")) else {
+        return Err("catalogue header needs ra, dec and mag columns".to_string());
+    };
+    let bv = column("bv");
+
+    lines
+        .map(|(number, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |index: usize| -> Result<f32, String> {
+                fields
+                    .get(index)
+                    .and_then(|value| value.parse::<f32>().ok())
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| format!("line {}: invalid or missing {}", number, columns[index]))
+            };
+            let star = CatalogueStar {
+                right_ascension: field(ra)?,
+                declination: field(dec)?,
+                magnitude: field(mag)?,
+                color_index: match bv {
+                    Some(index) => field(index)?,
+                    None => 0.6,
+                },
+            };
+            if !(-90.0..=90.0).contains(&star.declination) {
+                return Err(format!("line {}: declination out of range", number));
+            }
+            Ok(star)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{look_at, perspective};
+
+    fn settings() -> StarfieldSettings {
+        StarfieldSettings { stars: 500, milky_way: 1.0, ..StarfieldSettings::default() }
+    }
+
+    #[test]
+    fn same_seed_same_sky() {
+        let first = Starfield::generate(&settings(), &[]);
+        let again = Starfield::generate(&settings(), &[]);
+        let other = Starfield::generate(&StarfieldSettings { seed: 2, ..settings() }, &[]);
+        let directions = |field: &Starfield| field.stars.iter().map(|star| star.direction).collect::<Vec<_>>();
+        assert_eq!(directions(&first), directions(&again));
+        assert_ne!(directions(&first), directions(&other));
+        //mas estrellas debiles que brillantes
+        let bright = first.stars.iter().filter(|star| star.light.max() > 3.0 * 10f32.powf(-1.2)).count();
+        assert!(bright < first.star_count() / 10, "{}", bright);
+    }
+
+    #[test]
+    fn stars_turn_with_the_camera() {
+        let star = CatalogueStar { right_ascension: 0.0, declination: 0.0, magnitude: 0.0, color_index: 0.6 };
+        let field = Starfield::generate(&StarfieldSettings::default(), &[star]);
+        let (width, height) = (64, 48);
+        let projection = perspective(width as f32 / height as f32, 0.8, 0.1, 1000.0);
+        let brightest = |target: Vec3| {
+            let sky_matrix = projection * look_at(&Vec3::zeros(), &target, &Vec3::y());
+            let mut background = vec![Vec3::zeros(); width * height];
+            field.draw(&mut background, width, height, &sky_matrix, 0.0);
+            let (index, light) = background
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.max().total_cmp(&b.1.max()))
+                .unwrap();
+            (light.max() > 0.0).then_some((index % width, index / width))
+        };
+        //ra = 0, dec = 0 es +x: mirando hacia alla la estrella queda al centro
+        let (x, y) = brightest(Vec3::x()).unwrap();
+        assert!(x.abs_diff(width / 2) <= 1 && y.abs_diff(height / 2) <= 1, "{} {}", x, y);
+        //girando un poco se corre hacia un lado; de espaldas no se ve
+        let (turned, _) = brightest(Vec3::new(1.0, 0.0, 0.2)).unwrap();
+        assert!(turned.abs_diff(width / 2) > 3);
+        assert_eq!(brightest(-Vec3::x()), None);
+    }
+
+    #[test]
+    fn reads_a_catalogue() {
+        let source = "# estrellas\nra,dec,mag,bv\n101.287,-16.716,-1.46,0.00\n\n279.234,38.784,0.03,0.00\n";
+        let stars = parse_catalogue(source).unwrap();
+        assert_eq!(stars.len(), 2);
+        assert_eq!(stars[0].magnitude, -1.46);
+        let error = parse_catalogue("ra,dec,mag\n1,2,x\n").unwrap_err();
+        assert!(error.contains("line 2"), "{}", error);
+        assert!(parse_catalogue("ra,mag\n1,2\n").is_err());
+    }
+
+    #[test]
+    fn cube_coordinates_invert_faces() {
+        for face in 0..6 {
+            let (back, u, v) = cube_coordinates(&face_direction(face, 0.3, -0.6));
+            assert_eq!(back, face);
+            assert!((u - 0.3).abs() < 1e-6 && (v + 0.6).abs() < 1e-6, "{} {} {}", face, u, v);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use nalgebra_glm::{Vec2, Vec3};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::color::srgb_to_linear;
+
+//Texturas: imagen RGB con canales de 0 a 1, sus mipmaps y como se muestrea.
+//Las coordenadas (u, v) van de 0 a 1 con v hacia abajo, como las deja `Obj`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    //el texel mas cercano (pixelado)
+    Nearest,
+    //promedio de los cuatro texels vecinos
+    #[default]
+    Bilinear,
+    //como la N64: interpola entre los tres texels del triangulo en que cae
+    //la muestra, mas barato y con la diagonal a la vista
+    #[serde(rename = "three_point")]
+    ThreePoint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Wrap {
+    //la imagen se repite (u = 1.25 es u = 0.25)
+    #[default]
+    Repeat,
+    //fuera de [0, 1] se estira el borde
+    Clamp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sampler {
+    pub filter: Filter,
+    pub wrap: Wrap,
+    pub mipmaps: bool,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler { filter: Filter::default(), wrap: Wrap::default(), mipmaps: true }
+    }
+}
+
+struct Level {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec3>,
+}
+
+impl Level {
+    fn texel(&self, x: i64, y: i64, wrap: Wrap) -> Vec3 {
+        let (x, y) = match wrap {
+            Wrap::Repeat => (x.rem_euclid(self.width as i64), y.rem_euclid(self.height as i64)),
+            Wrap::Clamp => (x.clamp(0, self.width as i64 - 1), y.clamp(0, self.height as i64 - 1)),
+        };
+        self.texels[y as usize * self.width + x as usize]
+    }
+
+    fn sample(&self, uv: &Vec2, sampler: &Sampler) -> Vec3 {
+        let x = uv.x * self.width as f32;
+        let y = uv.y * self.height as f32;
+        match sampler.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64, sampler.wrap),
+            Filter::Bilinear => {
+                //centros de texel en .5
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = self.texel(x0, y0, sampler.wrap).lerp(&self.texel(x0 + 1, y0, sampler.wrap), tx);
+                let bottom = self.texel(x0, y0 + 1, sampler.wrap).lerp(&self.texel(x0 + 1, y0 + 1, sampler.wrap), tx);
+                top.lerp(&bottom, ty)
+            }
+            Filter::ThreePoint => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let right = self.texel(x0 + 1, y0, sampler.wrap);
+                let below = self.texel(x0, y0 + 1, sampler.wrap);
+                //triangulo de arriba a la izquierda o de abajo a la derecha
+                if tx + ty <= 1.0 {
+                    let corner = self.texel(x0, y0, sampler.wrap);
+                    corner + (right - corner) * tx + (below - corner) * ty
+                } else {
+                    let corner = self.texel(x0 + 1, y0 + 1, sampler.wrap);
+                    corner + (below - corner) * (1.0 - tx) + (right - corner) * (1.0 - ty)
+                }
+            }
+        }
+    }
+
+    //promedio de bloques de 2x2 (el borde impar se repite)
+    fn downsample(&self) -> Level {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (x, y) = (x as i64 * 2, y as i64 * 2);
+                let sum = self.texel(x, y, Wrap::Clamp)
+                    + self.texel(x + 1, y, Wrap::Clamp)
+                    + self.texel(x, y + 1, Wrap::Clamp)
+                    + self.texel(x + 1, y + 1, Wrap::Clamp);
+                texels.push(sum * 0.25);
+            }
+        }
+        Level { width, height, texels }
+    }
+}
+
+pub struct Texture {
+    //nivel 0 = imagen original, cada uno la mitad del anterior hasta 1x1
+    levels: Vec<Level>,
+    pub sampler: Sampler,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, texels: Vec<Vec3>, sampler: Sampler) -> Self {
+        assert!(width > 0 && height > 0, "texture must not be empty");
+        assert_eq!(texels.len(), width * height, "texel count must match the size");
+        let mut levels = vec![Level { width, height, texels }];
+        if sampler.mipmaps {
+            while let Some(last) = levels.last().filter(|level| level.width > 1 || level.height > 1) {
+                let next = last.downsample();
+                levels.push(next);
+            }
+        }
+        Texture { levels, sampler }
+    }
+
+    //PNG o JPEG; la transparencia se descarta. Las imagenes de color (`srgb`)
+    //se pasan a luz lineal; normal y specular maps son datos y quedan igual.
+    pub fn load(path: &str, sampler: Sampler, srgb: bool) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.to_rgb8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        if width == 0 || height == 0 {
+            return Err(image::ImageError::Limits(image::error::LimitError::from_kind(
+                image::error::LimitErrorKind::DimensionError,
+            )));
+        }
+        let decode = |value: u8| {
+            let value = value as f32 / 255.0;
+            if srgb { srgb_to_linear(value) } else { value }
+        };
+        let texels = image
+            .pixels()
+            .map(|pixel| Vec3::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2])))
+            .collect();
+        Ok(Texture::new(width, height, texels, sampler))
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    //`footprint` es cuanto avanza uv por pixel de pantalla: elige el mipmap.
+    //Con filtro bilineal o de tres puntos mezcla los dos niveles vecinos.
+    pub fn sample(&self, uv: &Vec2, footprint: f32) -> Vec3 {
+        if self.levels.len() == 1 || footprint.is_nan() || footprint <= 0.0 {
+            return self.levels[0].sample(uv, &self.sampler);
+        }
+        let texels_per_pixel = footprint * self.width().max(self.height()) as f32;
+        let lod = texels_per_pixel.log2().clamp(0.0, (self.levels.len() - 1) as f32);
+        match self.sampler.filter {
+            Filter::Nearest => self.levels[lod.round() as usize].sample(uv, &self.sampler),
+            Filter::Bilinear | Filter::ThreePoint => {
+                let lower = lod.floor() as usize;
+                let upper = (lower + 1).min(self.levels.len() - 1);
+                let near = self.levels[lower].sample(uv, &self.sampler);
+                let far = self.levels[upper].sample(uv, &self.sampler);
+                near.lerp(&far, lod - lower as f32)
+            }
+        }
+    }
+}
+
+//Mapas de un objeto; los que falten los reemplaza el shader procedural
+#[derive(Clone, Default)]
+pub struct TextureSet {
+    //albedo y night van en sRGB
+    pub albedo: Option<Arc<Texture>>,
+    //normales en espacio tangente (verde hacia arriba, como OpenGL)
+    pub normal: Option<Arc<Texture>>,
+    //intensidad especular en el canal rojo
+    pub specular: Option<Arc<Texture>>,
+    //luces de ciudades, solo se ven del lado de noche
+    pub night: Option<Arc<Texture>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker(sampler: Sampler) -> Texture {
+        let texels = (0..16)
+            .map(|i| if (i % 4 + i / 4) % 2 == 0 { Vec3::zeros() } else { Vec3::repeat(1.0) })
+            .collect();
+        Texture::new(4, 4, texels, sampler)
+    }
+
+    #[test]
+    fn nearest_bilinear_and_three_point_sampling() {
+        let nearest = checker(Sampler { filter: Filter::Nearest, wrap: Wrap::Repeat, mipmaps: false });
+        assert_eq!(nearest.sample(&Vec2::new(0.1, 0.1), 0.0), Vec3::zeros());
+        assert_eq!(nearest.sample(&Vec2::new(0.3, 0.1), 0.0), Vec3::repeat(1.0));
+
+        let bilinear = checker(Sampler { filter: Filter::Bilinear, wrap: Wrap::Repeat, mipmaps: false });
+        //en el centro de un texel da el texel; en la esquina entre cuatro, el promedio
+        assert_eq!(bilinear.sample(&Vec2::new(0.125, 0.125), 0.0), Vec3::zeros());
+        assert!((bilinear.sample(&Vec2::new(0.25, 0.25), 0.0) - Vec3::repeat(0.5)).norm() < 1e-6);
+
+        //tres puntos: entre cuatro texels solo cuentan los dos de la diagonal
+        let three_point = checker(Sampler { filter: Filter::ThreePoint, wrap: Wrap::Repeat, mipmaps: false });
+        assert_eq!(three_point.sample(&Vec2::new(0.125, 0.125), 0.0), Vec3::zeros());
+        assert_eq!(three_point.sample(&Vec2::new(0.25, 0.25), 0.0), Vec3::repeat(1.0));
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let repeat = checker(Sampler { filter: Filter::Nearest, wrap: Wrap::Repeat, mipmaps: false });
+        let clamp = checker(Sampler { filter: Filter::Nearest, wrap: Wrap::Clamp, mipmaps: false });
+        let outside = Vec2::new(1.3, 0.1);
+        assert_eq!(repeat.sample(&outside, 0.0), repeat.sample(&Vec2::new(0.3, 0.1), 0.0));
+        assert_eq!(clamp.sample(&outside, 0.0), clamp.sample(&Vec2::new(0.99, 0.1), 0.0));
+    }
+
+    #[test]
+    fn mipmaps_average_down_to_one_texel() {
+        let texture = checker(Sampler::default());
+        assert_eq!(texture.level_count(), 3);
+        //visto de lejos (un pixel cubre toda la imagen) el tablero es gris
+        let far = texture.sample(&Vec2::new(0.1, 0.1), 1.0);
+        assert!((far - Vec3::repeat(0.5)).norm() < 1e-6);
+        assert_eq!(texture.sample(&Vec2::new(0.125, 0.125), 0.25 / 4.0), Vec3::zeros());
+    }
+
+    #[test]
+    fn loads_png() {
+        let path = std::env::temp_dir().join("spacetravel_texture_test.png");
+        let mut image = image::RgbImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        image.put_pixel(1, 0, image::Rgb([0, 0, 255]));
+        image.save(&path).unwrap();
+
+        let sampler = Sampler { filter: Filter::Nearest, wrap: Wrap::Clamp, mipmaps: false };
+        let texture = Texture::load(path.to_str().unwrap(), sampler, false).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!((texture.width(), texture.height()), (2, 1));
+        assert_eq!(texture.sample(&Vec2::new(0.25, 0.5), 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.sample(&Vec2::new(0.75, 0.5), 0.0), Vec3::new(0.0, 0.0, 1.0));
+    }
+}
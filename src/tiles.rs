@@ -0,0 +1,181 @@
+use nalgebra_glm::Vec3;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::antialiasing::SamplePattern;
+use crate::fragment::{Fragment, FragmentSink};
+use crate::framebuffer::Framebuffer;
+use crate::shaders::ShaderProgram;
+use crate::triangle::{calculate_bounding_box, triangle, ScreenRect};
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+//Rasterizador por tiles: los triangulos se reparten en cuadros de pantalla y
+//cada hilo toma tiles libres. Cada tile trabaja sobre su propia copia de
+//color y profundidad y respeta el orden de los triangulos, asi el resultado
+//es identico al de un solo hilo.
+
+pub const TILE_SIZE: usize = 32;
+
+//Color y profundidad de las muestras de una region (el framebuffer o un
+//tile) mas los shaders: la prueba de profundidad va antes del fragment shader
+pub struct ShadingTarget<'a> {
+    pub rect: ScreenRect,
+    pub pattern: SamplePattern,
+    pub color: &'a mut [Vec3],
+    pub depth: &'a mut [f32],
+    pub uniforms: &'a Uniforms,
+    pub shader: &'a ShaderProgram,
+}
+
+impl ShadingTarget<'_> {
+    //primera muestra del pixel
+    fn index(&self, x: usize, y: usize) -> usize {
+        ((y - self.rect.y0) * self.rect.width() + (x - self.rect.x0)) * self.pattern.count()
+    }
+}
+
+impl FragmentSink for ShadingTarget<'_> {
+    fn pattern(&self) -> SamplePattern {
+        self.pattern
+    }
+
+    fn depth_test(&mut self, x: usize, y: usize, sample: usize, depth: f32) -> bool {
+        let i = self.index(x, y) + sample;
+        if self.depth[i] > depth {
+            self.depth[i] = depth;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn shade(&mut self, fragment: &Fragment, coverage: u32) {
+        let first = self.index(fragment.position.x as usize, fragment.position.y as usize);
+        let color = self.shader.fragment.shade(fragment, self.uniforms).to_vec3();
+        for sample in 0..self.pattern.count() {
+            if coverage & (1 << sample) != 0 {
+                self.color[first + sample] = color;
+            }
+        }
+    }
+}
+
+struct Tile {
+    rect: ScreenRect,
+    //indices de los triangulos que tocan el tile, en orden de dibujo
+    triangles: Vec<usize>,
+    color: Vec<Vec3>,
+    depth: Vec<f32>,
+}
+
+impl Tile {
+    fn rasterize(
+        &mut self,
+        pattern: SamplePattern,
+        vertices: &[Vertex],
+        triangles: &[[usize; 3]],
+        uniforms: &Uniforms,
+        shader: &ShaderProgram,
+    ) {
+        let mut target = ShadingTarget {
+            rect: self.rect,
+            pattern,
+            color: &mut self.color,
+            depth: &mut self.depth,
+            uniforms,
+            shader,
+        };
+        for &index in &self.triangles {
+            let [a, b, c] = triangles[index];
+            triangle(&vertices[a], &vertices[b], &vertices[c], &self.rect, &mut target);
+        }
+    }
+}
+
+pub fn render_tiles(
+    framebuffer: &mut Framebuffer,
+    vertices: &[Vertex],
+    triangles: &[[usize; 3]],
+    uniforms: &Uniforms,
+    shader: &ShaderProgram,
+    threads: usize,
+) {
+    let mut tiles = bin_triangles(framebuffer, vertices, triangles);
+    if tiles.is_empty() {
+        return;
+    }
+
+    let pattern = framebuffer.antialiasing().pattern();
+    let workers = threads.clamp(1, tiles.len());
+    let queue = Mutex::new(tiles.iter_mut());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let Some(tile) = queue.lock().unwrap().next() else { break };
+                tile.rasterize(pattern, vertices, triangles, uniforms, shader);
+            });
+        }
+    });
+
+    let samples = pattern.count();
+    for tile in &tiles {
+        let width = tile.rect.width() * samples;
+        for y in tile.rect.y0..tile.rect.y1 {
+            let row = (y * framebuffer.width + tile.rect.x0) * samples;
+            let local = (y - tile.rect.y0) * width;
+            framebuffer.samples[row..row + width].copy_from_slice(&tile.color[local..local + width]);
+            framebuffer.zbuffer[row..row + width].copy_from_slice(&tile.depth[local..local + width]);
+        }
+    }
+}
+
+//reparte cada triangulo en los tiles que cubre su caja; solo devuelve tiles con trabajo
+fn bin_triangles(framebuffer: &Framebuffer, vertices: &[Vertex], triangles: &[[usize; 3]]) -> Vec<Tile> {
+    let screen = ScreenRect::new(framebuffer.width, framebuffer.height);
+    let columns = framebuffer.width.div_ceil(TILE_SIZE);
+    let rows = framebuffer.height.div_ceil(TILE_SIZE);
+    let mut bins = vec![Vec::new(); columns * rows];
+
+    for (index, &[a, b, c]) in triangles.iter().enumerate() {
+        let (min_x, min_y, max_x, max_y) = calculate_bounding_box(
+            &vertices[a].transformed_position,
+            &vertices[b].transformed_position,
+            &vertices[c].transformed_position,
+            &screen,
+        );
+        if min_x > max_x || min_y > max_y {
+            continue;
+        }
+        for row in min_y as usize / TILE_SIZE..=max_y as usize / TILE_SIZE {
+            for column in min_x as usize / TILE_SIZE..=max_x as usize / TILE_SIZE {
+                bins[row * columns + column].push(index);
+            }
+        }
+    }
+
+    let samples = framebuffer.antialiasing().samples();
+    let mut tiles = Vec::new();
+    for (bin, triangles) in bins.into_iter().enumerate() {
+        if triangles.is_empty() {
+            continue;
+        }
+        let (x0, y0) = ((bin % columns) * TILE_SIZE, (bin / columns) * TILE_SIZE);
+        let rect = ScreenRect {
+            x0,
+            y0,
+            x1: (x0 + TILE_SIZE).min(framebuffer.width),
+            y1: (y0 + TILE_SIZE).min(framebuffer.height),
+        };
+        let width = rect.width() * samples;
+        let mut color = Vec::with_capacity(width * rect.height());
+        let mut depth = Vec::with_capacity(width * rect.height());
+        for y in rect.y0..rect.y1 {
+            let row = (y * framebuffer.width + rect.x0) * samples;
+            color.extend_from_slice(&framebuffer.samples[row..row + width]);
+            depth.extend_from_slice(&framebuffer.zbuffer[row..row + width]);
+        }
+        tiles.push(Tile { rect, triangles, color, depth });
+    }
+    tiles
+}
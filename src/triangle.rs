@@ -1,67 +1,145 @@
-use nalgebra_glm::{Vec3, dot};
-use crate::fragment::Fragment;
+use nalgebra_glm::{Vec2, Vec3};
+use crate::fragment::{Fragment, FragmentSink};
 use crate::vertex::Vertex;
-use crate::color::Color;
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-  let mut fragments = Vec::new();
-  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
-
-  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-
-  let light_dir = Vec3::new(0.0, 0.0, 1.0);
-
-  let triangle_area = edge_function(&a, &b, &c);
-
-  for y in min_y..=max_y {
-    for x in min_x..=max_x {
-      let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+//Rectangulo de pixeles [x0, x1) x [y0, y1): el framebuffer entero o un tile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenRect {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
 
-      let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+impl ScreenRect {
+    pub fn new(width: usize, height: usize) -> Self {
+        ScreenRect { x0: 0, y0: 0, x1: width, y1: height }
+    }
 
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
+    pub fn width(&self) -> usize {
+        self.x1 - self.x0
+    }
 
-        let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-        let normal = normal.normalize();
+    pub fn height(&self) -> usize {
+        self.y1 - self.y0
+    }
+}
 
-        let intensity = dot(&normal, &light_dir).max(0.0);
+//Recorre los pixeles del triangulo dentro de `rect` y le pasa cada uno al
+//`sink`: primero la profundidad de cada muestra y, si alguna pasa, el
+//fragmento interpolado. Cada pixel sale igual sin importar el rectangulo,
+//asi los tiles dan lo mismo que el framebuffer entero.
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, rect: &ScreenRect, sink: &mut impl FragmentSink) {
+  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
-        let base_color = Color::new(100, 100, 100);
-        let lit_color = base_color * intensity;
+  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c, rect);
 
-        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+  let triangle_area = edge_function(&a, &b, &c);
+  let pattern = sink.pattern();
+  //con SSAA cada muestra cubre una parte del pixel: mipmap mas fino
+  let uv_footprint = if pattern.shade_per_sample {
+    uv_footprint(v1, v2, v3, triangle_area) / (pattern.count() as f32).sqrt()
+  } else {
+    uv_footprint(v1, v2, v3, triangle_area)
+  };
+
+  //1/w de cada vertice para interpolar con correccion de perspectiva
+  let (inv_w1, inv_w2, inv_w3) = (1.0 / v1.clip_position.w, 1.0 / v2.clip_position.w, 1.0 / v3.clip_position.w);
+
+  //atributos en un punto de pantalla que esta dentro del triangulo
+  let interpolate = |x: i32, y: i32, point: &Vec3| {
+    let (w1, w2, w3) = barycentric_coordinates(point, &a, &b, &c, triangle_area);
+    //la profundidad es lineal en pantalla; el resto de atributos no
+    let depth = a.z * w1 + b.z * w2 + c.z * w3;
+
+    let (p1, p2, p3) = (w1 * inv_w1, w2 * inv_w2, w3 * inv_w3);
+    let sum = p1 + p2 + p3;
+    let (p1, p2, p3) = (p1 / sum, p2 / sum, p3 / sum);
+
+    let normal = v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3;
+    let normal = normal.normalize();
+
+    Fragment {
+      position: Vec2::new(x as f32, y as f32),
+      depth,
+      vertex_position: v1.position * p1 + v2.position * p2 + v3.position * p3,
+      world_position: v1.world_position * p1 + v2.world_position * p2 + v3.world_position * p3,
+      normal,
+      tangent: v1.transformed_tangent * p1 + v2.transformed_tangent * p2 + v3.transformed_tangent * p3,
+      tex_coords: v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3,
+      vertex_light: v1.color.to_vec3() * p1 + v2.color.to_vec3() * p2 + v3.color.to_vec3() * p3,
+      uv_footprint,
+    }
+  };
 
-        let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+  for y in min_y..=max_y {
+    for x in min_x..=max_x {
+      //early-Z por muestra: lo tapado no se interpola ni se sombrea
+      let mut coverage = 0u32;
+      for (sample, offset) in pattern.offsets().iter().enumerate() {
+        let point = Vec3::new(x as f32 + offset.x, y as f32 + offset.y, 0.0);
+
+        let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+
+        if (0.0..=1.0).contains(&w1) &&
+           (0.0..=1.0).contains(&w2) &&
+           (0.0..=1.0).contains(&w3) {
+          let depth = a.z * w1 + b.z * w2 + c.z * w3;
+          if sink.depth_test(x as usize, y as usize, sample, depth) {
+            coverage |= 1 << sample;
+          }
+        }
+      }
+      if coverage == 0 {
+        continue;
+      }
 
-        fragments.push(
-            Fragment::new(
-                x as f32,
-                y as f32,
-                lit_color,
-                depth,
-                normal,
-                intensity,
-                vertex_position,
-            )
-        );
+      if pattern.shade_per_sample {
+        for (sample, offset) in pattern.offsets().iter().enumerate() {
+          if coverage & (1 << sample) != 0 {
+            let point = Vec3::new(x as f32 + offset.x, y as f32 + offset.y, 0.0);
+            sink.shade(&interpolate(x, y, &point), 1 << sample);
+          }
+        }
+      } else {
+        //un solo sombreado en el centroide de las muestras visibles, que
+        //siempre cae dentro del triangulo
+        let mut centroid = Vec2::zeros();
+        for (sample, offset) in pattern.offsets().iter().enumerate() {
+          if coverage & (1 << sample) != 0 {
+            centroid += offset;
+          }
+        }
+        let centroid = centroid / coverage.count_ones() as f32;
+        let point = Vec3::new(x as f32 + centroid.x, y as f32 + centroid.y, 0.0);
+        sink.shade(&interpolate(x, y, &point), coverage);
       }
     }
   }
 
-  fragments
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
-    let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
-    let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
-    let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
-    let max_y = v1.y.max(v2.y).max(v3.y).ceil() as i32;
+//recortada al rectangulo: nunca recorre pixeles fuera del framebuffer
+pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3, rect: &ScreenRect) -> (i32, i32, i32, i32) {
+    let min_x = (v1.x.min(v2.x).min(v3.x).floor() as i32).max(rect.x0 as i32);
+    let min_y = (v1.y.min(v2.y).min(v3.y).floor() as i32).max(rect.y0 as i32);
+    let max_x = (v1.x.max(v2.x).max(v3.x).ceil() as i32).min(rect.x1 as i32 - 1);
+    let max_y = (v1.y.max(v2.y).max(v3.y).ceil() as i32).min(rect.y1 as i32 - 1);
 
     (min_x, min_y, max_x, max_y)
 }
 
+//raiz de area en uv / area en pantalla: un valor por triangulo, sin
+//derivadas por pixel
+fn uv_footprint(v1: &Vertex, v2: &Vertex, v3: &Vertex, screen_area: f32) -> f32 {
+    let (uv1, uv2) = (v2.tex_coords - v1.tex_coords, v3.tex_coords - v1.tex_coords);
+    let uv_area = uv1.x * uv2.y - uv2.x * uv1.y;
+    if screen_area == 0.0 {
+        return 0.0;
+    }
+    (uv_area / screen_area).abs().sqrt()
+}
+
 fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
     let w1 = edge_function(b, c, p) / area;
     let w2 = edge_function(c, a, p) / area;
@@ -72,4 +150,71 @@ fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) ->
 
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::antialiasing::{Antialiasing, SamplePattern};
+
+    //prueba de profundidad comun y cuantas veces se sombreo cada pixel
+    struct CountingSink {
+        pattern: SamplePattern,
+        depth: Vec<f32>,
+        shaded: Vec<u32>,
+    }
+
+    const SIZE: usize = 16;
+
+    impl FragmentSink for CountingSink {
+        fn pattern(&self) -> SamplePattern {
+            self.pattern
+        }
+
+        fn depth_test(&mut self, x: usize, y: usize, sample: usize, depth: f32) -> bool {
+            let i = (y * SIZE + x) * self.pattern.count() + sample;
+            let passes = depth < self.depth[i];
+            if passes {
+                self.depth[i] = depth;
+            }
+            passes
+        }
+
+        fn shade(&mut self, fragment: &Fragment, _coverage: u32) {
+            self.shaded[fragment.position.y as usize * SIZE + fragment.position.x as usize] += 1;
+        }
+    }
+
+    //mitad de la pantalla a la profundidad `depth`
+    fn draw(sink: &mut CountingSink, depth: f32) {
+        let corner = |x: f32, y: f32| Vertex { transformed_position: Vec3::new(x, y, depth), ..Vertex::default() };
+        let rect = ScreenRect::new(SIZE, SIZE);
+        triangle(&corner(0.0, 0.0), &corner(SIZE as f32, 0.0), &corner(0.0, SIZE as f32), &rect, sink);
+    }
+
+    #[test]
+    fn hidden_fragments_are_not_shaded() {
+        for antialiasing in [Antialiasing::None, Antialiasing::Msaa(4), Antialiasing::Ssaa(2)] {
+            let pattern = antialiasing.pattern();
+            let new_sink = || CountingSink {
+                pattern,
+                depth: vec![f32::INFINITY; SIZE * SIZE * pattern.count()],
+                shaded: vec![0; SIZE * SIZE],
+            };
+
+            //de adelante hacia atras: el de atras no llega al shader
+            let mut front_to_back = new_sink();
+            draw(&mut front_to_back, 0.2);
+            let front = front_to_back.shaded.clone();
+            assert!(front.iter().any(|&count| count > 0));
+            draw(&mut front_to_back, 0.8);
+            assert_eq!(front_to_back.shaded, front, "{:?}", antialiasing);
+
+            //al reves se sombrean los dos
+            let mut back_to_front = new_sink();
+            draw(&mut back_to_front, 0.8);
+            draw(&mut back_to_front, 0.2);
+            let twice: Vec<u32> = front.iter().map(|count| count * 2).collect();
+            assert_eq!(back_to_front.shaded, twice, "{:?}", antialiasing);
+        }
+    }
+}
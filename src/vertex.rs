@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -6,9 +6,18 @@ pub struct Vertex {
   pub position: Vec3,
   pub normal: Vec3,
   pub tex_coords: Vec2,
+  //con sombreado Gouraud, la luz difusa que llega al vertice
   pub color: Color,
+  //direccion de u en espacio de modelo; w = +-1 da el sentido de v (normal maps)
+  pub tangent: Vec4,
+  //salida del vertex shader en espacio de recorte (antes de dividir por w)
+  pub clip_position: Vec4,
+  //posicion en pantalla, la calcula `render` despues del recorte
   pub transformed_position: Vec3,
+  //posicion y normal en mundo, para la iluminacion
+  pub world_position: Vec3,
   pub transformed_normal: Vec3,
+  pub transformed_tangent: Vec4,
 }
 
 impl Vertex {
@@ -18,26 +27,30 @@ impl Vertex {
       normal,
       tex_coords,
       color: Color::black(),
+      tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
       transformed_position: position,
+      world_position: position,
       transformed_normal: normal,
+      transformed_tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
     }
   }
 
-  pub fn new_with_color(position: Vec3, color: Color) -> Self {
+  //interpola todos los atributos; lo usa el recorte para crear vertices nuevos
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
     Vertex {
-      position,
-      normal: Vec3::new(0.0, 0.0, 0.0),
-      tex_coords: Vec2::new(0.0, 0.0),
-      color,
-      transformed_position: Vec3::new(0.0, 0.0, 0.0),
-      transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      position: self.position.lerp(&other.position, t),
+      normal: self.normal.lerp(&other.normal, t),
+      tex_coords: self.tex_coords.lerp(&other.tex_coords, t),
+      color: self.color.lerp(&other.color, t),
+      tangent: self.tangent.lerp(&other.tangent, t),
+      clip_position: self.clip_position.lerp(&other.clip_position, t),
+      transformed_position: self.transformed_position.lerp(&other.transformed_position, t),
+      world_position: self.world_position.lerp(&other.world_position, t),
+      transformed_normal: self.transformed_normal.lerp(&other.transformed_normal, t),
+      transformed_tangent: self.transformed_tangent.lerp(&other.transformed_tangent, t),
     }
   }
-
-  pub fn set_transformed(&mut self, position: Vec3, normal: Vec3) {
-    self.transformed_position = position;
-    self.transformed_normal = normal;
-  }
 }
 
 impl Default for Vertex {
@@ -47,8 +60,12 @@ impl Default for Vertex {
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color: Color::black(),
+      tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      transformed_tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
     }
   }
 }
\ No newline at end of file